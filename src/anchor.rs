@@ -0,0 +1,99 @@
+use crate::Edit;
+
+/// Which way an [`Anchor`] moves when its position is ambiguous after an
+/// edit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Bias {
+    /// Towards the start of the document.
+    #[default]
+    Left,
+    /// Towards the end of the document.
+    Right,
+}
+
+/// A byte position in a document which follows edits, so that spans,
+/// diagnostics and the like remain valid as the document changes.
+///
+/// Anchors keep their position relative to the surrounding text when it is
+/// not replaced. The [`Bias`] decides where an anchor goes when text is
+/// inserted exactly at its position, or when the text around it is replaced:
+///
+/// ```rust
+/// # use logos_ropey::{Anchor, Bias, Edit};
+/// let mut left = Anchor::new(3, Bias::Left);
+/// let mut right = Anchor::new(3, Bias::Right);
+///
+/// // Insert two bytes at offset 3.
+/// let edit = Edit::new(3..3, 2);
+/// left.apply_edit(&edit);
+/// right.apply_edit(&edit);
+///
+/// assert_eq!(left.offset, 3);
+/// assert_eq!(right.offset, 5);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Anchor {
+    pub offset: usize,
+    pub bias: Bias,
+}
+
+impl Anchor {
+    pub fn new(offset: usize, bias: Bias) -> Self {
+        Self { offset, bias }
+    }
+
+    /// Moves the anchor to its position after `edit`.
+    ///
+    /// Anchors strictly inside the replaced range move to the start of the
+    /// new text if left-biased, or to its end if right-biased. So do anchors
+    /// at an insertion point.
+    pub fn apply_edit(&mut self, edit: &Edit) {
+        let (start, end) = (edit.range.start, edit.range.end);
+
+        self.offset = if self.offset < start
+            || (self.offset == start && (start != end || self.bias == Bias::Left))
+        {
+            self.offset
+        } else if self.offset >= end {
+            edit.shift(self.offset)
+        } else {
+            match self.bias {
+                Bias::Left => start,
+                Bias::Right => start + edit.new_len,
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_edit() {
+        use Bias::*;
+
+        let cases = [
+            // Before and after a replacement.
+            (Edit::new(4..6, 3), 3, Left, 3),
+            (Edit::new(4..6, 3), 7, Right, 8),
+            // At the ends of a replacement.
+            (Edit::new(4..6, 3), 4, Right, 4),
+            (Edit::new(4..6, 3), 6, Left, 7),
+            // Inside a replacement.
+            (Edit::new(4..8, 3), 5, Left, 4),
+            (Edit::new(4..8, 3), 5, Right, 7),
+            // At an insertion point.
+            (Edit::new(4..4, 3), 4, Left, 4),
+            (Edit::new(4..4, 3), 4, Right, 7),
+            // Inside a deletion.
+            (Edit::new(4..8, 0), 6, Right, 4),
+        ];
+
+        for (edit, offset, bias, expected) in cases {
+            let mut anchor = Anchor::new(offset, bias);
+            anchor.apply_edit(&edit);
+            assert_eq!(anchor.offset, expected, "{edit:?} {offset} {bias:?}");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use ::ariadne::{Cache, Source};
+use ropey::Rope;
+
+/// An [`ariadne::Cache`] of ropes, which only prepares a rope for rendering
+/// the first time a report refers to it.
+///
+/// Ariadne needs the whole text of each source, with its own line index.
+/// Ropes stored in a single chunk are borrowed, but other ropes have to be
+/// copied, so this only saves copying ropes which no report refers to.
+pub struct RopeCache<'r, Id> {
+    sources: HashMap<Id, Entry<'r>>,
+}
+
+/// A rope, and its source once a report has referred to it.
+struct Entry<'r> {
+    rope: &'r Rope,
+    source: Option<Source<Cow<'r, str>>>,
+}
+
+impl<'r, Id: Hash + Eq> RopeCache<'r, Id> {
+    pub fn new() -> Self {
+        Self {
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Adds a rope, replacing any previous rope with the same ID.
+    pub fn insert(&mut self, id: Id, rope: &'r Rope) {
+        self.sources.insert(id, Entry { rope, source: None });
+    }
+}
+
+impl<'r, Id: Hash + Eq> Default for RopeCache<'r, Id> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'r, Id: Hash + Eq> FromIterator<(Id, &'r Rope)> for RopeCache<'r, Id> {
+    fn from_iter<I: IntoIterator<Item = (Id, &'r Rope)>>(iter: I) -> Self {
+        let mut cache = Self::new();
+        for (id, rope) in iter {
+            cache.insert(id, rope);
+        }
+        cache
+    }
+}
+
+impl<'r, Id: Hash + Eq + Display> Cache<Id> for RopeCache<'r, Id> {
+    type Storage = Cow<'r, str>;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<Cow<'r, str>>, Box<dyn Debug + '_>> {
+        match self.sources.get_mut(id) {
+            Some(Entry { rope, source }) => {
+                let rope: &'r Rope = *rope;
+                Ok(source.get_or_insert_with(|| Source::from(Cow::from(rope.slice(..)))))
+            }
+            None => Err(Box::new(format!("no rope with ID {id}"))),
+        }
+    }
+
+    fn display<'a>(&self, id: &'a Id) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::ariadne::{Config, Label, Report, ReportKind};
+
+    use super::*;
+    use crate::test_util::char_rope;
+
+    #[test]
+    fn test_cache() {
+        let text = "fn main() {\n    let é = \"€😀\";\n}\n".repeat(3);
+        let rope = char_rope(&text);
+
+        let report = Report::build(ReportKind::Error, "main.rs", 20)
+            .with_config(Config::default().with_color(false))
+            .with_message("unused variable")
+            .with_label(Label::new(("main.rs", 20..22)).with_message("here"))
+            .finish();
+
+        let mut expected = Vec::new();
+        report
+            .write(
+                ::ariadne::sources([("main.rs", text.as_str())]),
+                &mut expected,
+            )
+            .unwrap();
+
+        let mut cache: RopeCache<_> = [("main.rs", &rope)].into_iter().collect();
+        let mut actual = Vec::new();
+        report.write(&mut cache, &mut actual).unwrap();
+
+        assert_eq!(
+            String::from_utf8(actual).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+        assert!(cache.fetch(&"lib.rs").is_err());
+    }
+}
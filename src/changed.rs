@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use crate::{Anchor, Bias, Edit};
+
+/// Returns the byte ranges of a document, after an edit, whose tokens
+/// changed, like tree-sitter's `changed_ranges`.
+///
+/// `old` and `new` are the spanned tokens of the document before and after
+/// `edit`, such as a token list before and after [`relex`](crate::relex).
+/// Old spans are mapped to the new document with [`Anchor`]s, so that a
+/// token spanning the edit covers all of the new text. A token is unchanged
+/// if an equal token has the mapped span in the new document, and the spans
+/// of all other old and new tokens are reported, as sorted ranges which
+/// neither overlap nor touch.
+///
+/// Text inserted within a token doesn't change its classification, so the
+/// token is unchanged if its span only grows by the inserted text. An old
+/// token within the edited range is reported as covering all of the text
+/// which replaced it, or as an empty range where it was if the edit only
+/// deleted text.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{changed_ranges, relex, Edit};
+/// #[derive(Logos, Debug, Clone, PartialEq)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+///     #[regex("[0-9]+")]
+///     Number,
+/// }
+///
+/// let old: Vec<_> = Token::lexer("one two three").spanned().collect();
+///
+/// // Replace "two" with "22".
+/// let edit = Edit::new(4..7, 2);
+/// let mut new = old.clone();
+/// relex::<Token>("one 22 three", &mut new, &edit);
+///
+/// assert_eq!(changed_ranges(&old, &new, &edit), [4..6]);
+/// ```
+pub fn changed_ranges<T: PartialEq>(
+    old: &[(T, Range<usize>)],
+    new: &[(T, Range<usize>)],
+    edit: &Edit,
+) -> Vec<Range<usize>> {
+    let map = |span: &Range<usize>| {
+        let mut start = Anchor::new(span.start, Bias::Left);
+        let mut end = Anchor::new(span.end, Bias::Right);
+        start.apply_edit(edit);
+        end.apply_edit(edit);
+        start.offset..end.offset
+    };
+
+    let mut changed = Vec::new();
+    let (mut old, mut new) = (old.iter().peekable(), new.iter().peekable());
+
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some((old_token, old_span)), Some((new_token, new_span))) => {
+                let old_span = map(old_span);
+                if old_token == new_token && old_span == *new_span {
+                    old.next();
+                    new.next();
+                } else if (old_span.start, old_span.end) <= (new_span.start, new_span.end) {
+                    changed.push(old_span);
+                    old.next();
+                } else {
+                    changed.push(new_span.clone());
+                    new.next();
+                }
+            }
+            (Some((_, old_span)), None) => {
+                changed.push(map(old_span));
+                old.next();
+            }
+            (None, Some((_, new_span))) => {
+                changed.push(new_span.clone());
+                new.next();
+            }
+            (None, None) => break,
+        }
+    }
+
+    merge(changed)
+}
+
+/// Merges overlapping or touching ranges, which are sorted by start.
+fn merge(ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_ranges() {
+        let old = [('w', 0..3), ('n', 4..6), ('w', 7..10)];
+        let cases = [
+            // "abc 12 def" to "abc xyz def".
+            (
+                Edit::new(4..6, 3),
+                vec![('w', 0..3), ('w', 4..7), ('w', 8..11)],
+                vec![4..7],
+            ),
+            // "abc 12 def" to "abcx 12 def".
+            (
+                Edit::new(3..3, 1),
+                vec![('w', 0..4), ('n', 5..7), ('w', 8..11)],
+                vec![],
+            ),
+            // "abc 12 def" to "a bc 12 def".
+            (
+                Edit::new(1..1, 1),
+                vec![('w', 0..1), ('w', 2..4), ('n', 5..7), ('w', 8..11)],
+                vec![0..4],
+            ),
+            // "abc 12 def" to "abc def".
+            (
+                Edit::new(4..7, 0),
+                vec![('w', 0..3), ('w', 4..7)],
+                vec![4..4],
+            ),
+            // "abc 12 def" to "abc     def", removing a token by replacing it
+            // with whitespace.
+            (
+                Edit::new(4..6, 3),
+                vec![('w', 0..3), ('w', 8..11)],
+                vec![4..7],
+            ),
+            // "abc 12 def" to "abcx12 def".
+            (
+                Edit::new(3..4, 1),
+                vec![('w', 0..4), ('n', 4..6), ('w', 7..10)],
+                vec![0..4],
+            ),
+        ];
+
+        for (edit, new, expected) in cases {
+            assert_eq!(changed_ranges(&old, &new, &edit), expected, "{edit:?}");
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+
+/// The state of a lexer between two tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint<E> {
+    /// Byte offset at which the next token starts to be lexed.
+    pub offset: usize,
+    pub extras: E,
+}
+
+impl<E: Clone> Checkpoint<E> {
+    /// Creates a lexer which continues from this checkpoint.
+    pub fn resume<'s, T>(&self, source: &'s T::Source) -> Lexer<'s, T>
+    where
+        T: Logos<'s, Extras = E>,
+    {
+        let mut lexer = Lexer::with_extras(source, self.extras.clone());
+        lexer.bump(self.offset);
+        lexer
+    }
+}
+
+/// [`Checkpoint`]s recorded at regular intervals while lexing, from which
+/// lexing can be resumed with the right [`Logos::Extras`].
+///
+/// This allows lexers which carry state in their extras, such as a nesting
+/// depth or mode, to restart in the middle of a document:
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::Checkpoints;
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(extras = usize)]
+/// enum Token {
+///     #[token("(", |lex| lex.extras += 1)]
+///     Open,
+///     #[token(")", |lex| lex.extras -= 1)]
+///     Close,
+///     #[regex("[a-z]", |lex| lex.extras)]
+///     Atom(usize),
+/// }
+///
+/// let source = "((a)b(c(d)))";
+/// let mut checkpoints = Checkpoints::new(4);
+/// let tokens: Vec<_> = checkpoints.record(Token::lexer(source)).collect();
+///
+/// let checkpoint = checkpoints.before(9).unwrap();
+/// assert_eq!(checkpoint.offset, 8);
+/// assert_eq!(checkpoint.extras, 3);
+///
+/// let resumed: Vec<_> = checkpoint.resume::<Token>(source).spanned().collect();
+/// assert_eq!(resumed, tokens[8..]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Checkpoints<E> {
+    interval: usize,
+    checkpoints: Vec<Checkpoint<E>>,
+}
+
+impl<E: Clone> Checkpoints<E> {
+    /// Creates an empty set of checkpoints, which will be recorded every
+    /// `interval` tokens.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(interval: usize) -> Self {
+        assert!(interval > 0, "checkpoint interval must be positive");
+        Self {
+            interval,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// The checkpoints, in order of their offsets.
+    pub fn as_slice(&self) -> &[Checkpoint<E>] {
+        &self.checkpoints
+    }
+
+    /// Returns the last checkpoint at or before `offset`.
+    pub fn before(&self, offset: usize) -> Option<&Checkpoint<E>> {
+        let index = self.checkpoints.partition_point(|c| c.offset <= offset);
+        self.checkpoints[..index].last()
+    }
+
+    /// Drops all checkpoints after `offset`, such as ones invalidated by an
+    /// edit.
+    pub fn truncate(&mut self, offset: usize) {
+        let index = self.checkpoints.partition_point(|c| c.offset <= offset);
+        self.checkpoints.truncate(index);
+    }
+
+    /// Wraps a lexer so that it records checkpoints, starting with its
+    /// current state.
+    ///
+    /// Checkpoints at or before the last existing one are skipped, so that
+    /// lexing can be resumed from [`Checkpoints::before`] after a
+    /// [`Checkpoints::truncate`].
+    pub fn record<'c, 's, T>(&'c mut self, lexer: Lexer<'s, T>) -> Recorder<'c, 's, T>
+    where
+        T: Logos<'s, Extras = E>,
+    {
+        let mut recorder = Recorder {
+            lexer,
+            checkpoints: self,
+            count: 0,
+        };
+        recorder.push();
+        recorder
+    }
+
+    fn push(&mut self, checkpoint: Checkpoint<E>) {
+        match self.checkpoints.last() {
+            Some(last) if last.offset >= checkpoint.offset => {}
+            _ => self.checkpoints.push(checkpoint),
+        }
+    }
+}
+
+/// An iterator over spanned tokens which records [`Checkpoints`], created by
+/// [`Checkpoints::record`].
+pub struct Recorder<'c, 's, T: Logos<'s>> {
+    lexer: Lexer<'s, T>,
+    checkpoints: &'c mut Checkpoints<T::Extras>,
+    /// Tokens lexed since the last checkpoint.
+    count: usize,
+}
+
+impl<'c, 's, T> Recorder<'c, 's, T>
+where
+    T: Logos<'s>,
+    T::Extras: Clone,
+{
+    /// The wrapped lexer.
+    pub fn lexer(&self) -> &Lexer<'s, T> {
+        &self.lexer
+    }
+
+    fn push(&mut self) {
+        self.checkpoints.push(Checkpoint {
+            offset: self.lexer.span().end,
+            extras: self.lexer.extras.clone(),
+        });
+    }
+}
+
+impl<'c, 's, T> Iterator for Recorder<'c, 's, T>
+where
+    T: Logos<'s>,
+    T::Extras: Clone,
+{
+    type Item = (Result<T, T::Error>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.lexer.next()?;
+        let span = self.lexer.span();
+
+        self.count += 1;
+        if self.count == self.checkpoints.interval {
+            self.count = 0;
+            self.push();
+        }
+
+        Some((token, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(extras = usize)]
+    #[logos(skip " ")]
+    enum Token {
+        #[token("{", |lex| lex.extras += 1)]
+        Open,
+        #[token("}", |lex| lex.extras -= 1)]
+        Close,
+        #[regex("[a-z]+", |lex| lex.extras)]
+        Word(usize),
+    }
+
+    #[test]
+    fn test_resume() {
+        let source = "a { b { c d } e { f } } g { h { i { j } } }".repeat(10);
+        let mut checkpoints = Checkpoints::new(3);
+        let tokens: Vec<_> = checkpoints.record(Token::lexer(&source)).collect();
+
+        assert_eq!(checkpoints.as_slice().len(), tokens.len() / 3 + 1);
+
+        for checkpoint in checkpoints.as_slice() {
+            let index = tokens.partition_point(|(_, span)| span.start < checkpoint.offset);
+            let resumed: Vec<_> = checkpoint.resume::<Token>(&source).spanned().collect();
+            assert_eq!(resumed, tokens[index..]);
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        let source = "a { b { c } } d".repeat(4);
+        let mut checkpoints = Checkpoints::new(2);
+        let tokens: Vec<_> = checkpoints.record(Token::lexer(&source)).collect();
+        let recorded = checkpoints.as_slice().to_vec();
+
+        checkpoints.truncate(20);
+        let checkpoint = checkpoints.before(30).unwrap().clone();
+        assert!(checkpoint.offset <= 20);
+
+        // Resuming records the same checkpoints again.
+        let resumed: Vec<_> = checkpoints
+            .record(checkpoint.resume::<Token>(&source))
+            .collect();
+        assert_eq!(checkpoints.as_slice(), recorded);
+
+        let index = tokens.len() - resumed.len();
+        assert_eq!(resumed, tokens[index..]);
+    }
+}
@@ -0,0 +1,517 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+use crate::leaf::{Leaf, LeafCache};
+use crate::stitch::Stitch;
+
+/// Text stored as a sequence of string chunks, such as a rope.
+///
+/// This is all [`ChunkedSource`] needs to implement [`logos::Source`], so
+/// that any rope library can be lexed by implementing it.
+///
+/// # Safety
+///
+/// [`ChunkedSource`] caches the chunks returned by [`ChunkedText::chunk_at`].
+/// The data they point to must therefore remain valid and unchanged for as
+/// long as the text is alive and not mutably borrowed, even if the text is
+/// moved. This is the case for ropes, whose chunks are stored in separate
+/// heap allocations.
+pub unsafe trait ChunkedText {
+    /// The type of slices of the text, which becomes the [`logos::Source`]
+    /// slice type.
+    type Slice<'a>: PartialEq + Eq + std::fmt::Debug
+    where
+        Self: 'a;
+
+    /// Length in bytes.
+    fn len_bytes(&self) -> usize;
+
+    /// Returns the non-empty chunk containing the byte at `offset` and the
+    /// chunk's starting byte offset.
+    ///
+    /// `offset` is always less than [`ChunkedText::len_bytes`], but may not be
+    /// on a char boundary.
+    fn chunk_at(&self, offset: usize) -> (&str, usize);
+
+    /// Returns the slice for a byte range, or `None` if it is out of bounds or
+    /// not on char boundaries.
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>>;
+}
+
+/// A [`logos::Source`] over any [`ChunkedText`].
+///
+/// Reads which straddle two chunks are served from small copies kept
+/// alongside the text, so lexing chunked text gives the same result as
+/// lexing the equivalent `&str` regardless of how the text is split.
+///
+/// Logos may hold on to the bytes of a read for as long as it borrows the
+/// source, so these copies are only freed when the source is dropped. Lexing
+/// a whole text therefore allocates up to 64 bytes for every chunk boundary,
+/// about one allocation per chunk, and making a copy takes a lock shared by
+/// all lexers of the source. To bound this memory for a long-lived text,
+/// such as a `RopeSource` kept for a document, create a new source for each
+/// lex rather than keeping one source around.
+///
+/// The chunk containing the most recent read is remembered, so that the
+/// lexer's mostly sequential reads only look up a chunk when they move into
+/// another one.
+///
+/// A source can also be restricted to a [window](ChunkedSource::window) of
+/// its text, such as a code block embedded in a larger document, while
+/// keeping spans relative to the whole text.
+pub struct ChunkedSource<T> {
+    text: T,
+    /// The byte range of the text which can be lexed.
+    window: Range<usize>,
+    leaf: LeafCache,
+    stitch: Stitch,
+    /// Whether a read has gone past the end of the window since the last
+    /// [`ChunkedSource::reset_read_past_end`]. This is only meaningful while
+    /// one lexer has the source to itself, which
+    /// [`StreamLexer`](crate::StreamLexer) ensures by borrowing it mutably.
+    read_past_end: AtomicBool,
+}
+
+// SAFETY: The leaf pointer only ever refers to data owned by the text, which
+// `ChunkedText` guarantees stays put.
+unsafe impl<T: Send> Send for ChunkedSource<T> {}
+// SAFETY: As above, and the caches are only changed through atomics and
+// locks, so lexers on other threads can share a source.
+unsafe impl<T: Sync> Sync for ChunkedSource<T> {}
+
+impl<T: ChunkedText> ChunkedSource<T> {
+    pub fn new(text: T) -> Self {
+        let window = 0..text.len_bytes();
+        Self::with_window(text, window)
+    }
+
+    /// Creates a source which only lexes the byte range `window` of `text`,
+    /// or returns `None` if it is out of bounds or not on char boundaries.
+    ///
+    /// Spans, and the ranges passed to [`logos::Source::slice`], are still
+    /// relative to the start of `text`, so tokens can be located in the whole
+    /// document. Use [`ChunkedSource::lexer`] to start lexing at the start of
+    /// the window.
+    ///
+    #[cfg_attr(feature = "ropey1", doc = "```rust")]
+    #[cfg_attr(not(feature = "ropey1"), doc = "```ignore")]
+    /// # use logos::Logos;
+    /// # use logos_ropey::RopeSliceSource;
+    /// #[derive(Logos, Debug, PartialEq)]
+    /// #[logos(source = RopeSliceSource<'s>)]
+    /// #[logos(skip " ")]
+    /// enum Token {
+    ///     #[regex("[a-z]+")]
+    ///     Word,
+    /// }
+    ///
+    /// let rope = ropey::Rope::from_str("one two three four");
+    /// let source = RopeSliceSource::window(rope.slice(..), 4..13).unwrap();
+    ///
+    /// let tokens: Vec<_> = source.lexer::<Token>().spanned().collect();
+    /// assert_eq!(tokens, [(Ok(Token::Word), 4..7), (Ok(Token::Word), 8..13)]);
+    /// ```
+    pub fn window(text: T, window: Range<usize>) -> Option<Self> {
+        text.slice(window.clone())?;
+        Some(Self::with_window(text, window))
+    }
+
+    fn with_window(text: T, window: Range<usize>) -> Self {
+        Self {
+            text,
+            window,
+            leaf: LeafCache::default(),
+            stitch: Stitch::default(),
+            read_past_end: AtomicBool::new(false),
+        }
+    }
+
+    /// The wrapped text.
+    pub fn text(&self) -> &T {
+        &self.text
+    }
+
+    pub fn into_text(self) -> T {
+        self.text
+    }
+
+    /// The byte range of the text which is lexed.
+    pub fn window_range(&self) -> Range<usize> {
+        self.window.clone()
+    }
+
+    /// Creates a lexer starting at the start of the window.
+    pub fn lexer<'s, Token>(&'s self) -> logos::Lexer<'s, Token>
+    where
+        Token: logos::Logos<'s, Source = Self>,
+        Token::Extras: Default,
+    {
+        let mut lexer = logos::Lexer::new(self);
+        lexer.bump(self.window.start);
+        lexer
+    }
+
+    /// Returns the text in `range` as a `str`, or `None` if the range is
+    /// outside the window or not on char boundaries.
+    ///
+    /// The text is borrowed when it lies within a single chunk, and only
+    /// copied when it spans several. This is useful for callbacks which need
+    /// a `&str`, as most tokens are much shorter than a chunk:
+    ///
+    #[cfg_attr(feature = "ropey1", doc = "```rust")]
+    #[cfg_attr(not(feature = "ropey1"), doc = "```ignore")]
+    /// # use logos::Logos;
+    /// # use logos_ropey::{ChunkedLexer, RopeSliceSource};
+    /// #[derive(Logos, Debug, PartialEq)]
+    /// #[logos(source = RopeSliceSource<'s>)]
+    /// #[logos(skip " ")]
+    /// enum Token {
+    ///     #[regex("[0-9]+", |lex| lex.slice_str().parse::<u64>().ok())]
+    ///     Number(u64),
+    /// }
+    ///
+    /// let rope = ropey::Rope::from_str("12 345");
+    /// let source = RopeSliceSource::from(&rope);
+    ///
+    /// let tokens: Vec<_> = source.lexer::<Token>().collect();
+    /// assert_eq!(tokens, [Ok(Token::Number(12)), Ok(Token::Number(345))]);
+    /// ```
+    pub fn slice_str(&self, range: Range<usize>) -> Option<Cow<'_, str>> {
+        // Check the bounds directly rather than with `Source::slice`, which
+        // may look up or even copy the slice.
+        if range.start < self.window.start
+            || range.start > range.end
+            || range.end > self.window.end
+            || !logos::Source::is_boundary(self, range.start)
+            || !logos::Source::is_boundary(self, range.end)
+        {
+            return None;
+        }
+        if range.is_empty() {
+            return Some(Cow::Borrowed(""));
+        }
+
+        let (chunk, start) = self.chunk_at(range.start);
+        if range.end <= start + chunk.len() {
+            let bytes = &chunk[range.start - start..range.end - start];
+            // SAFETY: The chunk is a `str` and the range is on char boundaries.
+            return Some(Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(bytes) }));
+        }
+
+        let mut bytes = vec![0; range.len()];
+        self.copy_bytes(range.start, &mut bytes);
+        // SAFETY: As above, the copied bytes are a whole number of chars.
+        Some(Cow::Owned(unsafe { String::from_utf8_unchecked(bytes) }))
+    }
+
+    /// Returns the bytes of the chunk containing `offset` and its starting
+    /// byte offset, cut off at the end of the window. `offset` must be less
+    /// than the end of the window.
+    fn chunk_at(&self, offset: usize) -> (&[u8], usize) {
+        if let Some(leaf) = self.leaf.get() {
+            if (leaf.start..leaf.start + leaf.len).contains(&offset) {
+                // SAFETY: `ChunkedText` guarantees the chunk is still valid.
+                let chunk = unsafe { std::slice::from_raw_parts(leaf.ptr, leaf.len) };
+                return (chunk, leaf.start);
+            }
+        }
+
+        let (chunk, start) = self.text.chunk_at(offset);
+        debug_assert!((start..start + chunk.len()).contains(&offset));
+
+        let chunk = &chunk.as_bytes()[..chunk.len().min(self.window.end - start)];
+        self.leaf.set(Leaf {
+            start,
+            ptr: chunk.as_ptr(),
+            len: chunk.len(),
+        });
+        (chunk, start)
+    }
+
+    /// Copies `buf.len()` bytes starting at `offset` into `buf`.
+    fn copy_bytes(&self, offset: usize, buf: &mut [u8]) {
+        let mut filled = 0;
+
+        while filled < buf.len() {
+            let (chunk, start) = self.text.chunk_at(offset + filled);
+            let bytes = &chunk.as_bytes()[offset + filled - start..];
+            let n = bytes.len().min(buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&bytes[..n]);
+            filled += n;
+        }
+    }
+}
+
+/// Extension methods for [`logos::Lexer`]s over a [`ChunkedSource`].
+pub trait ChunkedLexer<'s> {
+    /// The text of the current token, borrowed from the source unless it
+    /// spans several chunks. See [`ChunkedSource::slice_str`].
+    fn slice_str(&self) -> Cow<'s, str>;
+}
+
+impl<'s, Token, T> ChunkedLexer<'s> for logos::Lexer<'s, Token>
+where
+    Token: logos::Logos<'s, Source = ChunkedSource<T>>,
+    T: ChunkedText,
+{
+    fn slice_str(&self) -> Cow<'s, str> {
+        self.source()
+            .slice_str(self.span())
+            .expect("token spans are on char boundaries")
+    }
+}
+
+/// A [`logos::Source`] whose lexing may start after its first byte, such as
+/// a [`ChunkedSource`] restricted to a [window](ChunkedSource::window).
+///
+/// Helpers which lex from the start of a source, such as
+/// [`relex`](crate::relex), start from here instead of from offset 0.
+pub trait LexStart {
+    /// The byte offset of the first byte which can be lexed.
+    fn lex_start(&self) -> usize;
+}
+
+impl LexStart for str {
+    fn lex_start(&self) -> usize {
+        0
+    }
+}
+
+impl LexStart for [u8] {
+    fn lex_start(&self) -> usize {
+        0
+    }
+}
+
+impl<T> LexStart for ChunkedSource<T> {
+    fn lex_start(&self) -> usize {
+        self.window.start
+    }
+}
+
+/// Creates a lexer starting at the [`LexStart::lex_start`] of `source`.
+pub(crate) fn lexer_at_start<'s, Token>(source: &'s Token::Source) -> logos::Lexer<'s, Token>
+where
+    Token: logos::Logos<'s>,
+    Token::Source: LexStart,
+    Token::Extras: Default,
+{
+    let mut lexer = logos::Lexer::new(source);
+    lexer.bump(source.lex_start());
+    lexer
+}
+
+impl<T: ChunkedText + Clone> Clone for ChunkedSource<T> {
+    fn clone(&self) -> Self {
+        Self::with_window(self.text.clone(), self.window.clone())
+    }
+}
+
+impl<T: PartialEq> PartialEq for ChunkedSource<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text && self.window == other.window
+    }
+}
+
+impl<T: Eq> Eq for ChunkedSource<T> {}
+
+impl<T: PartialOrd> PartialOrd for ChunkedSource<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.text.partial_cmp(&other.text)? {
+            Ordering::Equal => Some(self.window_key().cmp(&other.window_key())),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl<T: Ord> Ord for ChunkedSource<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.text
+            .cmp(&other.text)
+            .then_with(|| self.window_key().cmp(&other.window_key()))
+    }
+}
+
+impl<T> ChunkedSource<T> {
+    fn window_key(&self) -> (usize, usize) {
+        (self.window.start, self.window.end)
+    }
+
+    pub(crate) fn reset_read_past_end(&mut self) {
+        *self.read_past_end.get_mut() = false;
+    }
+
+    /// Whether the lexer has tried to read past the end of the window, so
+    /// that the last token might have continued if the text were longer.
+    pub(crate) fn read_past_end(&self) -> bool {
+        self.read_past_end.load(AtomicOrdering::Relaxed)
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for ChunkedSource<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChunkedSource")
+            .field("text", &self.text)
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for ChunkedSource<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+impl<T: ChunkedText> From<T> for ChunkedSource<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ChunkedText> logos::Source for ChunkedSource<T> {
+    type Slice<'a> = T::Slice<'a> where T: 'a;
+
+    fn len(&self) -> usize {
+        self.window.end
+    }
+
+    fn read<'a, Chunk>(&'a self, offset: usize) -> Option<Chunk>
+    where
+        Chunk: logos::source::Chunk<'a>,
+    {
+        let len = self.len();
+        if offset < self.window.start {
+            return None;
+        }
+        if offset > len || Chunk::SIZE > len - offset {
+            self.read_past_end.store(true, AtomicOrdering::Relaxed);
+            return None;
+        }
+
+        let (chunk, start) = self.chunk_at(offset);
+        let data = &chunk[offset - start..];
+
+        let ptr = if data.len() >= Chunk::SIZE {
+            data.as_ptr()
+        } else {
+            self.stitch
+                .get(offset, Chunk::SIZE, len, |buf| self.copy_bytes(offset, buf))
+        };
+
+        Some(unsafe { Chunk::from_ptr(ptr) })
+    }
+
+    unsafe fn read_unchecked<'a, Chunk>(&'a self, offset: usize) -> Chunk
+    where
+        Chunk: logos::source::Chunk<'a>,
+    {
+        self.read(offset).unwrap_unchecked()
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        if range.start < self.window.start || range.end > self.window.end {
+            return None;
+        }
+
+        self.text.slice(range)
+    }
+
+    unsafe fn slice_unchecked(&self, range: Range<usize>) -> Self::Slice<'_> {
+        self.slice(range).unwrap_unchecked()
+    }
+
+    fn find_boundary(&self, index: usize) -> usize {
+        if index >= self.len() {
+            return self.len();
+        }
+
+        // Chunks are `str`s, so they never split a char and the end of a chunk
+        // is always a boundary.
+        let (chunk, start) = self.chunk_at(index);
+        chunk[index - start..]
+            .iter()
+            .position(|&b| !is_continuation(b))
+            .map_or(start + chunk.len(), |i| index + i)
+    }
+
+    fn is_boundary(&self, index: usize) -> bool {
+        match index.cmp(&self.len()) {
+            Ordering::Less => {
+                let (chunk, start) = self.chunk_at(index);
+                !is_continuation(chunk[index - start])
+            }
+            Ordering::Equal => true,
+            Ordering::Greater => false,
+        }
+    }
+}
+
+/// Whether `byte` is a UTF-8 continuation byte, i.e. of the form `0b10xxxxxx`.
+pub(crate) fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Checks shared by the tests of every [`ChunkedText`] implementation.
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fmt::Debug;
+    use std::ops::Range;
+
+    use logos::{Lexer, Logos};
+
+    /// The grammar which implementations' tests should also lex their sources
+    /// with, so that tokens can be compared by their `Debug` output.
+    #[derive(Logos, Debug)]
+    #[logos(skip r"[ \n]+")]
+    pub(crate) enum StrToken {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    /// Texts long enough to span many chunks of any rope, shifted by prefixes
+    /// of different lengths so that chunk boundaries fall inside keywords,
+    /// words and multi-byte chars.
+    pub(crate) fn texts() -> impl Iterator<Item = String> {
+        let body = "function functional funct é€😀 wordé\nfunctionalé 😀function\n";
+        let body = body.repeat(300);
+        ["", "a", "é", "€ ", "😀 a"]
+            .into_iter()
+            .map(move |prefix| format!("{prefix}{body}"))
+    }
+
+    /// Ranges of chars spread through `text`, which a test can delete and
+    /// insert again to move its rope's chunk boundaries without changing the
+    /// text.
+    pub(crate) fn edits(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+        text.char_indices()
+            .step_by(37)
+            .map(|(i, c)| i..i + c.len_utf8())
+    }
+
+    /// Asserts that lexing `source` with `T` gives the same tokens and spans as
+    /// lexing `text` with [`StrToken`].
+    pub(crate) fn assert_lexes_like_str<'s, T>(source: &'s T::Source, text: &str)
+    where
+        T: Logos<'s> + Debug,
+        T::Extras: Default,
+        T::Error: Debug,
+    {
+        let expected: Vec<_> = Lexer::<StrToken>::new(text)
+            .spanned()
+            .map(|(t, span)| (format!("{t:?}"), span))
+            .collect();
+        let actual: Vec<_> = Lexer::<T>::new(source)
+            .spanned()
+            .map(|(t, span)| (format!("{t:?}"), span))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,171 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use ::codespan_reporting::files::{Error, Files};
+use ropey::Rope;
+
+/// A [`codespan_reporting::files::Files`] database of ropes, like
+/// `SimpleFiles` but without copying each rope into a `String`.
+///
+/// Lines, and the locations of diagnostics, are looked up with the ropes' own
+/// line indices. Lines are split wherever Ropey considers there to be a line
+/// break.
+///
+/// Rendering a diagnostic still asks for the whole [`Files::source`] of its
+/// file, which borrows the rope if it is stored in a single chunk, but has
+/// to copy it otherwise. The copy is made the first time it is needed and
+/// kept for later diagnostics, so files which are only used for locations are
+/// never copied.
+#[derive(Clone, Debug)]
+pub struct RopeFiles<'r, N> {
+    files: Vec<File<'r, N>>,
+}
+
+#[derive(Clone, Debug)]
+struct File<'r, N> {
+    name: N,
+    rope: &'r Rope,
+    /// A copy of the rope's text, if it has more than one chunk and its
+    /// source has been asked for.
+    text: OnceLock<String>,
+}
+
+impl<'r, N> RopeFiles<'r, N> {
+    pub fn new() -> Self {
+        Self { files: Vec::new() }
+    }
+
+    /// Adds a file and returns its ID.
+    pub fn add(&mut self, name: N, rope: &'r Rope) -> usize {
+        self.files.push(File {
+            name,
+            rope,
+            text: OnceLock::new(),
+        });
+        self.files.len() - 1
+    }
+
+    /// Returns the name and rope of a file.
+    pub fn get(&self, id: usize) -> Result<(&N, &'r Rope), Error> {
+        let file = self.file(id)?;
+        Ok((&file.name, file.rope))
+    }
+
+    fn file(&self, id: usize) -> Result<&File<'r, N>, Error> {
+        self.files.get(id).ok_or(Error::FileMissing)
+    }
+
+    fn rope(&self, id: usize) -> Result<&'r Rope, Error> {
+        Ok(self.file(id)?.rope)
+    }
+}
+
+impl<'r, N> Default for RopeFiles<'r, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, 'r, N> Files<'a> for RopeFiles<'r, N>
+where
+    N: 'a + std::fmt::Display + Clone,
+{
+    type FileId = usize;
+    type Name = N;
+    type Source = &'a str;
+
+    fn name(&'a self, id: usize) -> Result<N, Error> {
+        Ok(self.get(id)?.0.clone())
+    }
+
+    fn source(&'a self, id: usize) -> Result<&'a str, Error> {
+        let file = self.file(id)?;
+        let mut chunks = file.rope.chunks();
+        match (chunks.next(), chunks.next()) {
+            (Some(chunk), None) => Ok(chunk),
+            (None, _) => Ok(""),
+            _ => Ok(file.text.get_or_init(|| file.rope.to_string())),
+        }
+    }
+
+    fn line_index(&'a self, id: usize, byte_index: usize) -> Result<usize, Error> {
+        let rope = self.rope(id)?;
+        rope.try_byte_to_line(byte_index)
+            .map_err(|_| Error::IndexTooLarge {
+                given: byte_index,
+                max: rope.len_bytes(),
+            })
+    }
+
+    fn line_range(&'a self, id: usize, line_index: usize) -> Result<Range<usize>, Error> {
+        let rope = self.rope(id)?;
+        Ok(line_start(rope, line_index)?..line_start(rope, line_index + 1)?)
+    }
+
+    fn column_number(
+        &'a self,
+        id: usize,
+        line_index: usize,
+        byte_index: usize,
+    ) -> Result<usize, Error> {
+        let rope = self.rope(id)?;
+        let line = self.line_range(id, line_index)?;
+        let end = byte_index.clamp(line.start, line.end);
+        Ok(rope.byte_to_char(end) - rope.byte_to_char(line.start) + 1)
+    }
+}
+
+/// Returns the byte offset of the start of a line, or the end of the rope for
+/// the line after the last one.
+fn line_start(rope: &Rope, line_index: usize) -> Result<usize, Error> {
+    let lines = rope.len_lines();
+    if line_index > lines {
+        return Err(Error::LineTooLarge {
+            given: line_index,
+            max: lines - 1,
+        });
+    }
+
+    Ok(rope.line_to_byte(line_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use ::codespan_reporting::files::SimpleFile;
+
+    use super::*;
+    use crate::test_util::char_rope;
+
+    #[test]
+    fn test_files() {
+        let text = "fn main() {\n    let é = \"€😀\";\n}\n".repeat(3);
+        let rope = char_rope(&text);
+
+        let mut files = RopeFiles::new();
+        let id = files.add("main.rs", &rope);
+        let simple = SimpleFile::new("main.rs", text.as_str());
+
+        assert_eq!(files.source(id).unwrap(), text);
+        // The copy of the rope is only made once.
+        assert!(std::ptr::eq(
+            files.source(id).unwrap(),
+            files.source(id).unwrap()
+        ));
+        for i in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+            assert_eq!(
+                files.location(id, i).unwrap(),
+                simple.location((), i).unwrap()
+            );
+        }
+        for line in 0..rope.len_lines() {
+            assert_eq!(
+                files.line_range(id, line).unwrap(),
+                simple.line_range((), line).unwrap()
+            );
+        }
+
+        assert!(files.line_range(id, rope.len_lines()).is_err());
+        assert!(files.line_index(id, text.len() + 1).is_err());
+        assert!(files.name(id + 1).is_err());
+    }
+}
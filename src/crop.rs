@@ -0,0 +1,107 @@
+use std::ops::Range;
+
+use crate::chunked::{is_continuation, ChunkedSource, ChunkedText};
+
+/// A [`logos::Source`] which wraps a [`crop::RopeSlice`].
+pub type CropSliceSource<'s> = ChunkedSource<::crop::RopeSlice<'s>>;
+
+/// A [`logos::Source`] which owns a [`crop::Rope`].
+pub type CropSource = ChunkedSource<::crop::Rope>;
+
+impl<'s> From<&'s ::crop::Rope> for CropSliceSource<'s> {
+    fn from(value: &'s ::crop::Rope) -> Self {
+        Self::new(value.byte_slice(..))
+    }
+}
+
+/// Returns the chunk containing the byte at `offset` and its starting offset.
+///
+/// Crop only slices at char boundaries, so this backs up to the start of the
+/// char containing `offset` and takes the first chunk of the rest of the rope.
+fn chunk_at<'a>(slice: ::crop::RopeSlice<'a>, offset: usize) -> (&'a str, usize) {
+    let mut start = offset;
+    while is_continuation(slice.byte(start)) {
+        start -= 1;
+    }
+
+    let chunk = slice
+        .byte_slice(start..)
+        .chunks()
+        .find(|chunk| !chunk.is_empty())
+        .expect("offset out of bounds");
+    (chunk, start)
+}
+
+/// Whether `range` is a valid slice of `slice`.
+fn is_valid(slice: ::crop::RopeSlice<'_>, range: &Range<usize>) -> bool {
+    let on_boundary = |i| i == slice.byte_len() || !is_continuation(slice.byte(i));
+    range.start <= range.end
+        && range.end <= slice.byte_len()
+        && on_boundary(range.start)
+        && on_boundary(range.end)
+}
+
+// SAFETY: Crop stores chunks in separate reference-counted leaves.
+unsafe impl<'s> ChunkedText for ::crop::RopeSlice<'s> {
+    type Slice<'a> = ::crop::RopeSlice<'a> where 's: 'a;
+
+    fn len_bytes(&self) -> usize {
+        self.byte_len()
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        chunk_at(*self, offset)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        is_valid(*self, &range).then(|| self.byte_slice(range))
+    }
+}
+
+// SAFETY: Crop stores chunks in separate reference-counted leaves.
+unsafe impl ChunkedText for ::crop::Rope {
+    type Slice<'a> = ::crop::RopeSlice<'a>;
+
+    fn len_bytes(&self) -> usize {
+        self.byte_len()
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        chunk_at(self.byte_slice(..), offset)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        is_valid(self.byte_slice(..), &range).then(|| self.byte_slice(range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked::tests::{assert_lexes_like_str, edits, texts};
+
+    #[derive(logos::Logos, Debug)]
+    #[logos(source = CropSource)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    #[test]
+    fn test_lexes_like_str() {
+        for text in texts() {
+            let mut rope = ::crop::Rope::from(text.as_str());
+            for range in edits(&text) {
+                rope.delete(range.clone());
+                rope.insert(range.start, &text[range]);
+            }
+
+            assert_lexes_like_str::<Token>(&CropSource::new(rope), &text);
+        }
+    }
+}
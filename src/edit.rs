@@ -0,0 +1,28 @@
+use std::ops::Range;
+
+/// A replacement of a byte range of a document with new text.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The replaced range, in the old document.
+    pub range: Range<usize>,
+    /// The length in bytes of the new text.
+    pub new_len: usize,
+}
+
+impl Edit {
+    pub fn new(range: Range<usize>, new_len: usize) -> Self {
+        Self { range, new_len }
+    }
+
+    /// The range of the new text, in the new document.
+    pub fn new_range(&self) -> Range<usize> {
+        self.range.start..self.range.start + self.new_len
+    }
+
+    /// Maps an offset at or after the end of the replaced range to the new
+    /// document.
+    pub fn shift(&self, offset: usize) -> usize {
+        debug_assert!(offset >= self.range.end);
+        offset - self.range.end + self.range.start + self.new_len
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Range;
+
+use ropey::RopeSlice;
+
+use crate::lsp::line_break_len;
+
+/// Splits the styled tokens of `text` into runs for each line in `lines`,
+/// for editors which render line by line.
+///
+/// Each line gets a list of `(columns, style)` runs, with columns counted in
+/// chars from the start of the line. Tokens spanning several lines, such as
+/// block comments, are split into a run on each line, and line breaks
+/// themselves are never highlighted. Tokens for which `style` returns `None`
+/// get no runs.
+///
+/// `tokens` must be sorted by span, as produced by a lexer.
+///
+/// # Panics
+///
+/// Panics if `lines` is out of bounds.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{highlight_lines, RopeSliceSource};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip r"[ \n]+")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+///     #[regex(r"/\*([^*]|\*[^/])*\*/")]
+///     Comment,
+/// }
+///
+/// let rope = ropey::Rope::from_str("one /* two\nthree */ four");
+/// let source = RopeSliceSource::from(&rope);
+/// let tokens: Vec<_> = source.lexer::<Token>().spanned().collect();
+///
+/// let runs = highlight_lines(rope.slice(..), &tokens, 0..2, |token| match token {
+///     Ok(Token::Comment) => Some("comment"),
+///     _ => None,
+/// });
+/// assert_eq!(runs, [vec![(4..10, "comment")], vec![(0..8, "comment")]]);
+/// ```
+pub fn highlight_lines<T, S: Clone>(
+    text: RopeSlice<'_>,
+    tokens: &[(T, Range<usize>)],
+    lines: Range<usize>,
+    mut style: impl FnMut(&T) -> Option<S>,
+) -> Vec<Vec<(Range<usize>, S)>> {
+    let start = text.line_to_byte(lines.start);
+    let end = text.line_to_byte(lines.end);
+    let mut runs = vec![Vec::new(); lines.len()];
+
+    let first = tokens.partition_point(|(_, span)| span.end <= start);
+    for (token, span) in &tokens[first..] {
+        if span.start >= end {
+            break;
+        }
+
+        let span = span.start.max(start)..span.end.min(end);
+        if span.is_empty() {
+            continue;
+        }
+        let style = match style(token) {
+            Some(style) => style,
+            None => continue,
+        };
+
+        let chars = text.byte_to_char(span.start)..text.byte_to_char(span.end);
+        for line in text.byte_to_line(span.start)..=text.byte_to_line(span.end - 1) {
+            let line_start = text.line_to_char(line);
+            let slice = text.line(line);
+            let content_end = line_start + slice.len_chars() - line_break_len(slice);
+
+            let run = chars.start.max(line_start)..chars.end.min(content_end);
+            if !run.is_empty() {
+                let columns = run.start - line_start..run.end - line_start;
+                runs[line - lines.start].push((columns, style.clone()));
+            }
+        }
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_lines() {
+        let rope = ropey::Rope::from_str("ab /* c\r\néé */ d\nx");
+        let tokens = [('i', 0..2), ('c', 3..16), ('i', 17..18), ('n', 19..20)];
+        let style = |&t: &char| (t != 'n').then_some(t);
+
+        assert_eq!(
+            highlight_lines(rope.slice(..), &tokens, 0..3, style),
+            [
+                vec![(0..2, 'i'), (3..7, 'c')],
+                vec![(0..5, 'c'), (6..7, 'i')],
+                vec![],
+            ],
+        );
+        assert_eq!(
+            highlight_lines(rope.slice(..), &tokens, 1..2, style),
+            [vec![(0..5, 'c'), (6..7, 'i')]],
+        );
+        assert_eq!(
+            highlight_lines(rope.slice(..), &tokens, 2..2, style),
+            Vec::<Vec<_>>::new(),
+        );
+    }
+}
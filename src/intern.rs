@@ -0,0 +1,200 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+use logos::{Lexer, Logos};
+use ropey::RopeSlice;
+
+use crate::RopeSliceSource;
+
+/// An interned string, which can be resolved by the [`Interner`] which
+/// created it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The index of the symbol, in the order in which strings were interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Interns strings, which can be looked up by rope slices without copying
+/// them.
+///
+/// Slices are hashed chunk by chunk, feeding the hasher the same bytes as
+/// hashing the equivalent `str`, so that only a miss allocates.
+///
+/// With an interner in the lexer's extras, [`intern_token`] can be used as a
+/// callback which interns the token:
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{intern_token, Interner, RopeSliceSource, Symbol};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(extras = Interner)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+", intern_token)]
+///     Ident(Symbol),
+/// }
+///
+/// let rope = ropey::Rope::from_str("foo bar foo");
+/// let source = RopeSliceSource::from(&rope);
+/// let mut lexer = source.lexer::<Token>();
+///
+/// let foo = lexer.next().unwrap().unwrap();
+/// let bar = lexer.next().unwrap().unwrap();
+/// assert_eq!(lexer.next(), Some(Ok(foo)));
+///
+/// let Token::Ident(bar) = bar;
+/// assert_eq!(lexer.extras.resolve(bar), "bar");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Interner<S = RandomState> {
+    strings: Vec<Box<str>>,
+    /// Symbols by the hash of their string.
+    symbols: HashMap<u64, Vec<Symbol>>,
+    hasher: S,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: BuildHasher> Interner<S> {
+    /// Creates an interner which hashes strings with `hasher`.
+    ///
+    /// Slices spanning several chunks are hashed with several writes, so the
+    /// hasher must produce the same hash however its input is split, as the
+    /// standard library's hashers do.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            strings: Vec::new(),
+            symbols: HashMap::new(),
+            hasher,
+        }
+    }
+
+    /// Returns the symbol for `slice`, interning it if it hasn't been already.
+    pub fn intern(&mut self, slice: RopeSlice<'_>) -> Symbol {
+        let hash = self.hash(slice);
+        if let Some(symbol) = self.find(hash, slice) {
+            return symbol;
+        }
+
+        let symbol = Symbol(
+            self.strings
+                .len()
+                .try_into()
+                .expect("too many interned strings"),
+        );
+        self.strings.push(String::from(slice).into_boxed_str());
+        self.symbols.entry(hash).or_default().push(symbol);
+        symbol
+    }
+
+    /// Returns the symbol for `string`, interning it if it hasn't been
+    /// already.
+    pub fn intern_str(&mut self, string: &str) -> Symbol {
+        self.intern(RopeSlice::from(string))
+    }
+
+    /// Returns the symbol for `slice` if it has been interned.
+    pub fn get(&self, slice: RopeSlice<'_>) -> Option<Symbol> {
+        self.find(self.hash(slice), slice)
+    }
+
+    /// Returns the string of a symbol created by this interner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the symbol wasn't created by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    /// The number of interned strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    fn find(&self, hash: u64, slice: RopeSlice<'_>) -> Option<Symbol> {
+        self.symbols
+            .get(&hash)?
+            .iter()
+            .copied()
+            .find(|&symbol| slice == self.resolve(symbol))
+    }
+
+    /// Hashes `slice` as `Hash for str` does: its bytes, then `0xff`.
+    fn hash(&self, slice: RopeSlice<'_>) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        for chunk in slice.chunks() {
+            hasher.write(chunk.as_bytes());
+        }
+        hasher.write_u8(0xff);
+        hasher.finish()
+    }
+}
+
+impl<S> AsMut<Interner<S>> for Interner<S> {
+    fn as_mut(&mut self) -> &mut Interner<S> {
+        self
+    }
+}
+
+/// A lexer callback which interns the token in the [`Interner`] in the
+/// lexer's extras.
+pub fn intern_token<'s, T, S>(lex: &mut Lexer<'s, T>) -> Symbol
+where
+    T: Logos<'s, Source = RopeSliceSource<'s>>,
+    T::Extras: AsMut<Interner<S>>,
+    S: BuildHasher,
+{
+    let slice = lex.slice();
+    lex.extras.as_mut().intern(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+
+    use super::*;
+    use crate::test_util::char_rope;
+
+    #[test]
+    fn test_intern() {
+        let text = "alpha beta gamma é€😀 alpha".repeat(3);
+        let rope = char_rope(&text);
+
+        let mut interner = Interner::new();
+        let words: Vec<_> = text.split(' ').collect();
+        let symbols: Vec<_> = words.iter().map(|w| interner.intern_str(w)).collect();
+        assert_eq!(interner.len(), 5);
+
+        let mut offset = 0;
+        for (word, &symbol) in words.iter().zip(&symbols) {
+            let slice = rope.byte_slice(offset..offset + word.len());
+            offset += word.len() + 1;
+
+            let mut hasher = interner.hasher.build_hasher();
+            word.hash(&mut hasher);
+            assert_eq!(interner.hash(slice), hasher.finish());
+
+            assert_eq!(interner.get(slice), Some(symbol));
+            assert_eq!(interner.intern(slice), symbol);
+            assert_eq!(interner.resolve(symbol), *word);
+        }
+
+        assert_eq!(interner.len(), 5);
+        assert_eq!(interner.get(RopeSlice::from("delta")), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use ropey::RopeSlice;
+
+/// A table of keywords, sorted at compile time, which can be queried with
+/// rope slices without copying them.
+///
+/// Keywords are sorted by length and then by their bytes, so a lookup only
+/// compares the slice against keywords of the same length, with a binary
+/// search, and slices longer than any keyword are rejected immediately.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{Keywords, RopeSliceSource};
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Keyword {
+///     Fn,
+///     Let,
+///     Return,
+/// }
+///
+/// static KEYWORDS: Keywords<Keyword, 3> = Keywords::new([
+///     ("fn", Keyword::Fn),
+///     ("let", Keyword::Let),
+///     ("return", Keyword::Return),
+/// ]);
+///
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+", |lex| KEYWORDS.get(lex.slice()))]
+///     Keyword(Keyword),
+/// }
+///
+/// let rope = ropey::Rope::from_str("let fn returns");
+/// let source = RopeSliceSource::from(&rope);
+///
+/// let tokens: Vec<_> = source.lexer::<Token>().collect();
+/// assert_eq!(
+///     tokens,
+///     [
+///         Ok(Token::Keyword(Keyword::Let)),
+///         Ok(Token::Keyword(Keyword::Fn)),
+///         Err(()),
+///     ],
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keywords<V: 'static, const N: usize> {
+    entries: [(&'static str, V); N],
+}
+
+impl<V: Copy, const N: usize> Keywords<V, N> {
+    /// Creates a table from keywords and their values.
+    ///
+    /// # Panics
+    ///
+    /// Panics, at compile time if used in a constant, if a keyword appears
+    /// more than once.
+    pub const fn new(mut entries: [(&'static str, V); N]) -> Self {
+        // Insertion sort, as the standard library's sorts can't be called in
+        // a `const fn`.
+        let mut i = 1;
+        while i < N {
+            let mut j = i;
+            while j > 0 && less(entries[j].0, entries[j - 1].0) {
+                let entry = entries[j];
+                entries[j] = entries[j - 1];
+                entries[j - 1] = entry;
+                j -= 1;
+            }
+            i += 1;
+        }
+
+        let mut i = 1;
+        while i < N {
+            if !less(entries[i - 1].0, entries[i].0) {
+                panic!("duplicate keyword");
+            }
+            i += 1;
+        }
+
+        Self { entries }
+    }
+
+    /// Returns the value of the keyword equal to `slice`.
+    pub fn get(&self, slice: RopeSlice<'_>) -> Option<V> {
+        let len = slice.len_bytes();
+        let start = self.entries.partition_point(|(k, _)| k.len() < len);
+        let end = start + self.entries[start..].partition_point(|(k, _)| k.len() == len);
+
+        let entries = &self.entries[start..end];
+        let i = entries.binary_search_by(|(k, _)| compare(k, slice)).ok()?;
+        Some(entries[i].1)
+    }
+
+    /// Returns the value of the keyword equal to `string`.
+    pub fn get_str(&self, string: &str) -> Option<V> {
+        self.get(RopeSlice::from(string))
+    }
+
+    /// The keywords and their values, sorted by length and then by bytes.
+    pub fn entries(&self) -> &[(&'static str, V)] {
+        &self.entries
+    }
+}
+
+/// Whether `a` sorts before `b`, by length and then by bytes.
+const fn less(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return a.len() < b.len();
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Compares a keyword with a slice of the same length, chunk by chunk.
+fn compare(keyword: &str, slice: RopeSlice<'_>) -> Ordering {
+    let mut rest = keyword.as_bytes();
+    for chunk in slice.chunks() {
+        let (head, tail) = rest.split_at(chunk.len());
+        match head.cmp(chunk.as_bytes()) {
+            Ordering::Equal => rest = tail,
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::char_rope;
+
+    static KEYWORDS: Keywords<usize, 8> = Keywords::new([
+        ("while", 0),
+        ("if", 1),
+        ("in", 2),
+        ("else", 3),
+        ("é", 4),
+        ("match", 5),
+        ("fn", 6),
+        ("elif", 7),
+    ]);
+
+    #[test]
+    fn test_keywords() {
+        let keys: Vec<_> = KEYWORDS.entries().iter().map(|(k, _)| *k).collect();
+        assert_eq!(
+            keys,
+            ["fn", "if", "in", "é", "elif", "else", "match", "while"]
+        );
+
+        let text = "while if in else é match fn elif i iff whilst matches ".repeat(3);
+        let rope = char_rope(&text);
+
+        let mut offset = 0;
+        for word in text.split_terminator(' ') {
+            let slice = rope.byte_slice(offset..offset + word.len());
+            offset += word.len() + 1;
+
+            let expected = KEYWORDS.entries().iter().find(|(k, _)| *k == word);
+            assert_eq!(KEYWORDS.get(slice), expected.map(|&(_, v)| v), "{word}");
+            assert_eq!(KEYWORDS.get_str(word), expected.map(|&(_, v)| v), "{word}");
+        }
+    }
+}
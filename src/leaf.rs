@@ -0,0 +1,69 @@
+use std::sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering};
+
+/// A chunk of a [`ChunkedSource`](crate::ChunkedSource)'s text.
+#[derive(Clone, Copy)]
+pub(crate) struct Leaf {
+    /// Byte offset of the chunk in the text.
+    pub(crate) start: usize,
+    pub(crate) ptr: *const u8,
+    pub(crate) len: usize,
+}
+
+/// The chunk most recently looked up by a source, which can be shared between
+/// threads.
+///
+/// The leaf is published with a sequence lock, so reading it takes a few
+/// atomic loads and never blocks. A reader which races with a writer, or a
+/// writer which races with another writer, treats the cache as missed rather
+/// than waiting, since the chunk can always be looked up again.
+#[derive(Default)]
+pub(crate) struct LeafCache {
+    /// Even while the leaf is stable, odd while it is being written.
+    seq: AtomicUsize,
+    start: AtomicUsize,
+    /// Null while no leaf has been cached.
+    ptr: AtomicPtr<u8>,
+    len: AtomicUsize,
+}
+
+impl LeafCache {
+    pub(crate) fn get(&self) -> Option<Leaf> {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq % 2 == 1 {
+            return None;
+        }
+
+        let leaf = Leaf {
+            start: self.start.load(Ordering::Relaxed),
+            ptr: self.ptr.load(Ordering::Relaxed),
+            len: self.len.load(Ordering::Relaxed),
+        };
+
+        // Order the loads above before checking that no write overlapped them.
+        fence(Ordering::Acquire);
+        if self.seq.load(Ordering::Relaxed) != seq || leaf.ptr.is_null() {
+            return None;
+        }
+        Some(leaf)
+    }
+
+    pub(crate) fn set(&self, leaf: Leaf) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        if seq % 2 == 1
+            || self
+                .seq
+                .compare_exchange(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+        {
+            // Another thread is caching its own leaf.
+            return;
+        }
+
+        // Order the stores below after marking the leaf as being written.
+        fence(Ordering::Release);
+        self.start.store(leaf.start, Ordering::Relaxed);
+        self.ptr.store(leaf.ptr.cast_mut(), Ordering::Relaxed);
+        self.len.store(leaf.len, Ordering::Relaxed);
+        self.seq.store(seq + 2, Ordering::Release);
+    }
+}
@@ -1,130 +1,118 @@
-use std::ops::Range;
-
-/// A [`logos::Source`] which wraps a [`ropey::RopeSlice`].
-///
-/// To use it, set the `source` attribute on your `logos` derive to
-/// `RopeSliceSource<'s>`:
-///
-/// ```rust
-/// # use logos::Logos;
-/// # use logos_ropey::RopeSliceSource;
-/// #[derive(Logos)]
-/// #[logos(source = RopeSliceSource<'s>)]
-/// enum Token {
-///     #[regex(".")]
-///     Token,
-/// }
-/// ```
-#[derive(ref_cast::RefCast, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
-#[repr(transparent)]
-pub struct RopeSliceSource<'s>(pub ropey::RopeSlice<'s>);
-
-impl<'s> std::fmt::Display for RopeSliceSource<'s> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
-    }
-}
-
-impl<'s> From<ropey::RopeSlice<'s>> for RopeSliceSource<'s> {
-    fn from(value: ropey::RopeSlice<'s>) -> Self {
-        Self(value)
-    }
-}
-
-impl<'s> From<&'s ropey::Rope> for RopeSliceSource<'s> {
-    fn from(value: &'s ropey::Rope) -> Self {
-        Self(value.slice(..))
-    }
-}
-
-impl<'s> logos::Source for RopeSliceSource<'s> {
-    type Slice<'a> = ropey::RopeSlice<'a> where 's: 'a;
-
-    fn len(&self) -> usize {
-        self.0.len_bytes()
-    }
-
-    fn read<'a, Chunk>(&'a self, offset: usize) -> Option<Chunk>
-    where
-        Chunk: logos::source::Chunk<'a>,
-    {
-        let (mut chunks, start, _, _) = self.0.chunks_at_byte(offset);
-        let data = &chunks.next()?.as_bytes()[offset - start..];
-
-        if data.len() < Chunk::SIZE {
-            None
-        } else {
-            Some(unsafe { Chunk::from_ptr(data.as_ptr()) })
-        }
-    }
-
-    unsafe fn read_unchecked<'a, Chunk>(&'a self, offset: usize) -> Chunk
-    where
-        Chunk: logos::source::Chunk<'a>,
-    {
-        self.read(offset).unwrap_unchecked()
-    }
-
-    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
-        self.0.get_byte_slice(range)
-    }
-
-    unsafe fn slice_unchecked(&self, range: Range<usize>) -> Self::Slice<'_> {
-        self.slice(range).unwrap_unchecked()
-    }
-
-    fn find_boundary(&self, index: usize) -> usize {
-        let c = self.0.byte_to_char(index);
-
-        if index == self.0.char_to_byte(c) {
-            index
-        } else {
-            self.0.char_to_byte(c + 1)
-        }
-    }
-
-    fn is_boundary(&self, index: usize) -> bool {
-        self.0
-            .try_byte_to_char(index)
-            .ok()
-            .map(|c| self.0.char_to_byte(c))
-            == Some(index)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[derive(logos::Logos, Debug, PartialEq)]
-    #[logos(source = RopeSliceSource<'s>)]
-    enum Token {
-        #[regex(r"[^,]*,")]
-        Token,
-    }
-
-    #[test]
-    fn test_source() {
-        let mut rope = ropey::Rope::new();
-
-        // Build a sufficiently large string that we exercise chunking.
-        for len in 1..=1_000 {
-            let mut token = str::repeat("x", len);
-            token.push_str(",");
-            rope.append(token.into());
-        }
-
-        // Make sure we have chunks.
-        assert!(rope.chunks().count() > 10);
-
-        let source = RopeSliceSource(rope.slice(..));
-        let lexer = logos::Lexer::new(&source);
-
-        assert_eq!(
-            lexer
-                .inspect(|t| assert_eq!(t.as_ref().ok(), Some(&Token::Token)))
-                .count(),
-            1_000
-        );
-    }
-}
+//! [`logos::Source`] implementations for ropes.
+//!
+//! Sources are built on [`ChunkedSource`], which can lex any text
+//! implementing [`ChunkedText`]. Implementations are provided for the
+//! following rope libraries, each behind a cargo feature:
+//!
+//! - Ropey 1, with the default `ropey1` feature, whose sources are exported at
+//!   the root of the crate.
+//! - Ropey 2, with the `ropey2` feature, in the [`ropey2`](mod@ropey2) module.
+//! - Crop, with the `crop` feature.
+//! - Xi, with the `xi-rope` feature.
+//!
+//! JumpRope is not supported. It only locates its chunks by char index, or
+//! by iterating over all of them, so finding the chunk containing a byte
+//! offset would make lexing quadratic in the number of chunks, and slices of
+//! text spanning chunks would have to be copied.
+//!
+//! With the `serde` feature, lexed tokens can be serialized as a
+//! `TokenStream`. Diagnostics can be rendered from ropes with
+//! codespan-reporting, using `RopeFiles` with the `codespan-reporting`
+//! feature, or with Ariadne, using `RopeCache` with the `ariadne` feature.
+//!
+//! The `testing` feature provides the `testing` module, for checking that a
+//! grammar lexes ropes the same however they are split into chunks.
+//!
+//! # Migrating from 0.0
+//!
+//! `RopeSliceSource` used to be a newtype around a `ropey::RopeSlice`, and
+//! is now an alias for a [`ChunkedSource`], which caches chunks and so is no
+//! longer a plain wrapper:
+//!
+//! - `RopeSliceSource(slice)` becomes `RopeSliceSource::new(slice)`, or
+//!   `slice.into()` as before.
+//! - `source.0` becomes `source.text()`, or `source.into_text()` to take the
+//!   slice.
+//! - Sources are `Clone` but not `Copy`. Lexers borrow their source, so
+//!   create the source once and pass a reference to it around instead.
+//! - `RopeSliceSource` no longer implements `RefCast`, and this crate no
+//!   longer depends on `ref-cast`. Create a source from the slice instead of
+//!   casting a reference to it.
+
+mod anchor;
+#[cfg(feature = "ariadne")]
+mod ariadne;
+mod changed;
+mod checkpoint;
+mod chunked;
+#[cfg(feature = "codespan-reporting")]
+mod codespan;
+#[cfg(feature = "crop")]
+mod crop;
+mod edit;
+#[cfg(feature = "ropey1")]
+mod highlight;
+#[cfg(feature = "ropey1")]
+mod intern;
+#[cfg(feature = "ropey1")]
+mod keyword;
+mod leaf;
+#[cfg(feature = "ropey1")]
+pub mod lsp;
+#[cfg(feature = "ropey1")]
+pub mod number;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "ropey1")]
+mod position;
+mod query;
+mod relex;
+#[cfg(feature = "ropey1")]
+mod ropey1;
+#[cfg(feature = "ropey2")]
+pub mod ropey2;
+mod stitch;
+#[cfg(feature = "ropey1")]
+mod store;
+mod stream;
+#[cfg(all(any(test, feature = "testing"), feature = "ropey1"))]
+mod test_util;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "serde")]
+mod token_stream;
+#[cfg(feature = "xi-rope")]
+mod xi_rope;
+
+pub use anchor::{Anchor, Bias};
+#[cfg(feature = "ariadne")]
+pub use ariadne::RopeCache;
+pub use changed::changed_ranges;
+pub use checkpoint::{Checkpoint, Checkpoints, Recorder};
+pub use chunked::{ChunkedLexer, ChunkedSource, ChunkedText, LexStart};
+#[cfg(feature = "codespan-reporting")]
+pub use codespan::RopeFiles;
+#[cfg(feature = "crop")]
+pub use crop::{CropSliceSource, CropSource};
+pub use edit::Edit;
+#[cfg(feature = "ropey1")]
+pub use highlight::highlight_lines;
+#[cfg(feature = "ropey1")]
+pub use intern::{intern_token, Interner, Symbol};
+#[cfg(feature = "ropey1")]
+pub use keyword::Keywords;
+#[cfg(feature = "rayon")]
+pub use parallel::lex_parallel;
+#[cfg(feature = "ropey1")]
+pub use position::Position;
+pub use query::{find_token, lex_token_at, token_at, TokenAt};
+pub use relex::{relex, relex_with_lookahead};
+#[cfg(feature = "ropey1")]
+pub use ropey1::{AsRopeSlice, RopeSliceSource, RopeSource};
+#[cfg(feature = "ropey1")]
+pub use store::{Spanned, TokenStore};
+pub use stream::{StreamLexer, Streamed};
+#[cfg(feature = "serde")]
+pub use token_stream::{content_hash, DeltaSpan, TokenStream};
+#[cfg(feature = "xi-rope")]
+pub use xi_rope::XiRopeSource;
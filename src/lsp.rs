@@ -0,0 +1,256 @@
+//! Conversions between byte offsets and [Language Server Protocol] positions.
+//!
+//! LSP positions count columns in code units of a
+//! [`PositionEncoding`] negotiated between client and server, which defaults
+//! to UTF-16. With the `lsp-types` feature, the types here convert to and
+//! from their `lsp_types` equivalents.
+//!
+//! [Language Server Protocol]: https://microsoft.github.io/language-server-protocol/
+
+use std::ops::Range as ByteRange;
+
+use crate::{AsRopeSlice, ChunkedSource, ChunkedText};
+
+/// How the columns of LSP positions are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PositionEncoding {
+    /// Bytes.
+    Utf8,
+    /// UTF-16 code units, the default in LSP.
+    #[default]
+    Utf16,
+    /// Chars.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Parses a `positionEncoding` value from LSP, such as `"utf-16"`.
+    pub fn from_lsp(kind: &str) -> Option<Self> {
+        match kind {
+            "utf-8" => Some(Self::Utf8),
+            "utf-16" => Some(Self::Utf16),
+            "utf-32" => Some(Self::Utf32),
+            _ => None,
+        }
+    }
+
+    /// The `positionEncoding` value for LSP.
+    pub fn as_lsp(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32",
+        }
+    }
+
+    /// Counts code units in `slice`.
+    fn len(self, slice: ropey::RopeSlice<'_>) -> usize {
+        match self {
+            Self::Utf8 => slice.len_bytes(),
+            Self::Utf16 => slice.len_utf16_cu(),
+            Self::Utf32 => slice.len_chars(),
+        }
+    }
+
+    /// Converts a code unit index in `slice` to a char index.
+    fn to_char(self, slice: ropey::RopeSlice<'_>, index: usize) -> usize {
+        match self {
+            Self::Utf8 => slice.byte_to_char(index),
+            Self::Utf16 => slice.utf16_cu_to_char(index),
+            Self::Utf32 => index,
+        }
+    }
+}
+
+/// A position in an LSP document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    /// The zero-based line.
+    pub line: u32,
+    /// The zero-based column, in code units of the [`PositionEncoding`].
+    pub character: u32,
+}
+
+/// A range in an LSP document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl<T: ChunkedText + AsRopeSlice> ChunkedSource<T> {
+    /// Returns the LSP position of a byte offset, or `None` if it is past the
+    /// end of the text.
+    ///
+    /// Offsets inside a char are positioned at the start of that char.
+    ///
+    /// ```rust
+    /// # use logos_ropey::RopeSource;
+    /// # use logos_ropey::lsp::{Position, PositionEncoding};
+    /// let source = RopeSource::new("😀 x".into());
+    ///
+    /// let position = |encoding| source.lsp_position(5, encoding).unwrap().character;
+    /// assert_eq!(position(PositionEncoding::Utf8), 5);
+    /// assert_eq!(position(PositionEncoding::Utf16), 3);
+    /// assert_eq!(position(PositionEncoding::Utf32), 2);
+    /// ```
+    pub fn lsp_position(&self, offset: usize, encoding: PositionEncoding) -> Option<Position> {
+        let slice = self.text().as_rope_slice();
+        let line = slice.try_byte_to_line(offset).ok()?;
+        let line_start = slice.line_to_char(line);
+        let before = slice.slice(line_start..slice.byte_to_char(offset));
+
+        Some(Position {
+            line: line.try_into().ok()?,
+            character: encoding.len(before).try_into().ok()?,
+        })
+    }
+
+    /// Returns the LSP range of a byte span, or `None` if it is out of
+    /// bounds.
+    pub fn lsp_range(&self, span: ByteRange<usize>, encoding: PositionEncoding) -> Option<Range> {
+        Some(Range {
+            start: self.lsp_position(span.start, encoding)?,
+            end: self.lsp_position(span.end, encoding)?,
+        })
+    }
+
+    /// Returns the byte offset of an LSP position, or `None` if its line is
+    /// out of bounds.
+    ///
+    /// As required by LSP, a column past the end of the line is treated as
+    /// the end of the line, and a column inside a char as the start of that
+    /// char.
+    pub fn lsp_offset(&self, position: Position, encoding: PositionEncoding) -> Option<usize> {
+        let slice = self.text().as_rope_slice();
+        let line_index = position.line.try_into().ok()?;
+        let mut line = slice.get_line(line_index)?;
+
+        // The end of the line is before its line break.
+        let content_len = line.len_chars() - line_break_len(line);
+        line = line.slice(..content_len);
+
+        let character = (position.character as usize).min(encoding.len(line));
+        let column = encoding.to_char(line, character);
+        Some(slice.char_to_byte(slice.line_to_char(line_index) + column))
+    }
+
+    /// Returns the byte span of an LSP range, or `None` if it is out of
+    /// bounds.
+    pub fn lsp_span(&self, range: Range, encoding: PositionEncoding) -> Option<ByteRange<usize>> {
+        Some(self.lsp_offset(range.start, encoding)?..self.lsp_offset(range.end, encoding)?)
+    }
+}
+
+/// Returns the number of chars in the line break ending `line`.
+pub(crate) fn line_break_len(line: ropey::RopeSlice<'_>) -> usize {
+    let mut chars = line.chars_at(line.len_chars()).reversed();
+    match (chars.next(), chars.next()) {
+        (Some('\n'), Some('\r')) => 2,
+        (Some(c), _) if is_line_break(c) => 1,
+        _ => 0,
+    }
+}
+
+/// Whether `c` is one of the line breaks recognized by Ropey.
+fn is_line_break(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\u{000B}' | '\u{000C}' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+#[cfg(feature = "lsp-types")]
+mod lsp_types_impls {
+    use super::*;
+
+    impl From<lsp_types::Position> for Position {
+        fn from(value: lsp_types::Position) -> Self {
+            Self {
+                line: value.line,
+                character: value.character,
+            }
+        }
+    }
+
+    impl From<Position> for lsp_types::Position {
+        fn from(value: Position) -> Self {
+            Self::new(value.line, value.character)
+        }
+    }
+
+    impl From<lsp_types::Range> for Range {
+        fn from(value: lsp_types::Range) -> Self {
+            Self {
+                start: value.start.into(),
+                end: value.end.into(),
+            }
+        }
+    }
+
+    impl From<Range> for lsp_types::Range {
+        fn from(value: Range) -> Self {
+            Self::new(value.start.into(), value.end.into())
+        }
+    }
+
+    impl From<PositionEncoding> for lsp_types::PositionEncodingKind {
+        fn from(value: PositionEncoding) -> Self {
+            Self::new(value.as_lsp())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RopeSliceSource;
+
+    #[test]
+    fn test_round_trip() {
+        let rope = ropey::Rope::from_str("aé€\r\n😀b\nc");
+        let source = RopeSliceSource::from(&rope);
+
+        let cases = [
+            (0, (0, 0), (0, 0), (0, 0)),
+            (3, (0, 3), (0, 2), (0, 2)),
+            (6, (0, 6), (0, 3), (0, 3)),
+            (8, (1, 0), (1, 0), (1, 0)),
+            (12, (1, 4), (1, 2), (1, 1)),
+            (13, (1, 5), (1, 3), (1, 2)),
+            (15, (2, 1), (2, 1), (2, 1)),
+        ];
+
+        for (offset, utf8, utf16, utf32) in cases {
+            for (encoding, (line, character)) in [
+                (PositionEncoding::Utf8, utf8),
+                (PositionEncoding::Utf16, utf16),
+                (PositionEncoding::Utf32, utf32),
+            ] {
+                let position = Position { line, character };
+                assert_eq!(source.lsp_position(offset, encoding), Some(position));
+                assert_eq!(source.lsp_offset(position, encoding), Some(offset));
+            }
+        }
+    }
+
+    #[test]
+    fn test_clamp_to_line() {
+        let rope = ropey::Rope::from_str("ab\r\ncd");
+        let source = RopeSliceSource::from(&rope);
+        let position = |line, character| Position { line, character };
+
+        assert_eq!(
+            source.lsp_offset(position(0, 10), PositionEncoding::Utf16),
+            Some(2)
+        );
+        assert_eq!(
+            source.lsp_offset(position(1, 10), PositionEncoding::Utf16),
+            Some(6)
+        );
+        assert_eq!(
+            source.lsp_offset(position(2, 0), PositionEncoding::Utf16),
+            None
+        );
+    }
+}
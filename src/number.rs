@@ -0,0 +1,268 @@
+//! Parsing numeric literals from rope slices without copying them into a
+//! `String`.
+//!
+//! Integers may have a `0x`, `0o` or `0b` radix prefix, and both integers and
+//! floats may contain `_` separators, as in Rust literals. [`int`] and
+//! [`float`] can be used directly as lexer callbacks:
+//!
+//! ```rust
+//! # use logos::Logos;
+//! # use logos_ropey::{number, RopeSliceSource};
+//! #[derive(Logos, Debug, PartialEq)]
+//! #[logos(source = RopeSliceSource<'s>)]
+//! #[logos(error = number::ParseIntError)]
+//! #[logos(skip " ")]
+//! enum Token {
+//!     #[regex("0x[0-9a-fA-F_]+|[0-9][0-9_]*", number::int)]
+//!     Int(u32),
+//! }
+//!
+//! let rope = ropey::Rope::from_str("1_000 0xff 99999999999");
+//! let source = RopeSliceSource::from(&rope);
+//!
+//! let tokens: Vec<_> = source.lexer::<Token>().collect();
+//! assert_eq!(
+//!     tokens,
+//!     [
+//!         Ok(Token::Int(1000)),
+//!         Ok(Token::Int(255)),
+//!         Err(number::ParseIntError::Overflow),
+//!     ],
+//! );
+//! ```
+
+use std::num::ParseFloatError;
+use std::str::FromStr;
+
+use logos::{Lexer, Logos};
+use ropey::RopeSlice;
+
+use crate::RopeSliceSource;
+
+/// Floats up to this many bytes long, without separators, are parsed from a
+/// buffer on the stack.
+const FLOAT_BUFFER: usize = 64;
+
+/// An error from [`parse_int`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ParseIntError {
+    /// There are no digits.
+    #[default]
+    Empty,
+    /// A character is not a digit in the radix, or a sign is not allowed.
+    InvalidDigit,
+    /// The number is too large or too small for the integer type.
+    Overflow,
+}
+
+impl std::fmt::Display for ParseIntError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Empty => "cannot parse integer from empty string",
+            Self::InvalidDigit => "invalid digit found in string",
+            Self::Overflow => "number too large or too small to fit in target type",
+        })
+    }
+}
+
+impl std::error::Error for ParseIntError {}
+
+/// Integer types which [`parse_int`] can produce.
+pub trait Int: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    const SIGNED: bool;
+    #[doc(hidden)]
+    const ZERO: Self;
+    /// Computes `self * radix + digit`, or `self * radix - digit` if
+    /// `negative`.
+    #[doc(hidden)]
+    fn push_digit(self, radix: u32, digit: u32, negative: bool) -> Option<Self>;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_int {
+    ($signed:literal: $($t:ty)*) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl Int for $t {
+            const SIGNED: bool = $signed;
+            const ZERO: Self = 0;
+
+            fn push_digit(self, radix: u32, digit: u32, negative: bool) -> Option<Self> {
+                let shifted = self.checked_mul(radix as Self)?;
+                if negative {
+                    shifted.checked_sub(digit as Self)
+                } else {
+                    shifted.checked_add(digit as Self)
+                }
+            }
+        }
+    )*};
+}
+
+impl_int!(false: u8 u16 u32 u64 u128 usize);
+impl_int!(true: i8 i16 i32 i64 i128 isize);
+
+/// Parses an integer with an optional sign, radix prefix and `_`
+/// separators.
+///
+/// A `-` sign is only allowed for signed types.
+pub fn parse_int<I: Int>(slice: RopeSlice<'_>) -> Result<I, ParseIntError> {
+    let mut bytes = slice.bytes().peekable();
+
+    let negative = match bytes.peek() {
+        Some(b'-') if I::SIGNED => true,
+        Some(b'-') => return Err(ParseIntError::InvalidDigit),
+        _ => false,
+    };
+    if matches!(bytes.peek(), Some(b'+' | b'-')) {
+        bytes.next();
+    }
+
+    let mut radix = 10;
+    if bytes.peek() == Some(&b'0') {
+        bytes.next();
+        radix = match bytes.peek() {
+            Some(b'x' | b'X') => 16,
+            Some(b'o' | b'O') => 8,
+            Some(b'b' | b'B') => 2,
+            // The zero is the first digit.
+            _ => return digits(bytes, 10, negative, true),
+        };
+        bytes.next();
+    }
+
+    digits(bytes, radix, negative, false)
+}
+
+/// Accumulates the remaining digits of an integer.
+fn digits<I: Int>(
+    bytes: impl Iterator<Item = u8>,
+    radix: u32,
+    negative: bool,
+    mut any: bool,
+) -> Result<I, ParseIntError> {
+    let mut value = I::ZERO;
+
+    for byte in bytes {
+        if byte == b'_' {
+            continue;
+        }
+
+        let digit = (byte as char)
+            .to_digit(radix)
+            .ok_or(ParseIntError::InvalidDigit)?;
+        value = value
+            .push_digit(radix, digit, negative)
+            .ok_or(ParseIntError::Overflow)?;
+        any = true;
+    }
+
+    if any {
+        Ok(value)
+    } else {
+        Err(ParseIntError::Empty)
+    }
+}
+
+/// Parses a float, as [`str::parse`] would after removing `_` separators.
+///
+/// The float is copied to the stack unless it is unusually long.
+pub fn parse_float<F>(slice: RopeSlice<'_>) -> Result<F, ParseFloatError>
+where
+    F: FromStr<Err = ParseFloatError>,
+{
+    let mut buffer = [0; FLOAT_BUFFER];
+    let mut len = 0;
+
+    for byte in slice.bytes().filter(|&b| b != b'_') {
+        if len == FLOAT_BUFFER {
+            let string: String = slice.chars().filter(|&c| c != '_').collect();
+            return string.parse();
+        }
+
+        buffer[len] = byte;
+        len += 1;
+    }
+
+    // SAFETY: Removing ASCII bytes from UTF-8 leaves valid UTF-8.
+    unsafe { std::str::from_utf8_unchecked(&buffer[..len]) }.parse()
+}
+
+/// A lexer callback which parses the token with [`parse_int`].
+pub fn int<'s, T, I>(lex: &mut Lexer<'s, T>) -> Result<I, ParseIntError>
+where
+    T: Logos<'s, Source = RopeSliceSource<'s>>,
+    I: Int,
+{
+    parse_int(lex.slice())
+}
+
+/// A lexer callback which parses the token with [`parse_float`].
+pub fn float<'s, T, F>(lex: &mut Lexer<'s, T>) -> Result<F, ParseFloatError>
+where
+    T: Logos<'s, Source = RopeSliceSource<'s>>,
+    F: FromStr<Err = ParseFloatError>,
+{
+    parse_float(lex.slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<I: Int>(text: &str) -> Result<I, ParseIntError> {
+        parse_int(RopeSlice::from(text))
+    }
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse::<u32>("0"), Ok(0));
+        assert_eq!(parse::<u32>("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse::<u32>("0xDead_beef"), Ok(0xdead_beef));
+        assert_eq!(parse::<u32>("0o777"), Ok(0o777));
+        assert_eq!(parse::<u32>("0b_1010"), Ok(0b1010));
+        assert_eq!(parse::<u32>("+012"), Ok(12));
+        assert_eq!(parse::<i8>("-128"), Ok(i8::MIN));
+        assert_eq!(parse::<i64>("-0x8000_0000_0000_0000"), Ok(i64::MIN));
+        assert_eq!(parse::<u128>(&u128::MAX.to_string()), Ok(u128::MAX));
+
+        assert_eq!(parse::<u32>(""), Err(ParseIntError::Empty));
+        assert_eq!(parse::<u32>("0x"), Err(ParseIntError::Empty));
+        assert_eq!(parse::<u32>("_"), Err(ParseIntError::Empty));
+        assert_eq!(parse::<u32>("-1"), Err(ParseIntError::InvalidDigit));
+        assert_eq!(parse::<u32>("0b102"), Err(ParseIntError::InvalidDigit));
+        assert_eq!(parse::<u32>("1é"), Err(ParseIntError::InvalidDigit));
+        assert_eq!(parse::<u8>("256"), Err(ParseIntError::Overflow));
+        assert_eq!(parse::<i8>("-129"), Err(ParseIntError::Overflow));
+    }
+
+    #[test]
+    fn test_parse_float() {
+        let long = format!("{}.5", "1".repeat(100));
+
+        for text in [
+            "0",
+            "1.5",
+            "1_000.25",
+            "-2.5e-3",
+            "inf",
+            "NaN",
+            "1e",
+            "é",
+            "",
+            long.as_str(),
+        ] {
+            let expected = text.replace('_', "").parse::<f64>();
+            let actual = parse_float::<f64>(RopeSlice::from(text));
+
+            match (actual, expected) {
+                (Ok(a), Ok(e)) => assert!(a == e || (a.is_nan() && e.is_nan()), "{text}"),
+                (actual, expected) => assert_eq!(actual.is_ok(), expected.is_ok(), "{text}"),
+            }
+        }
+    }
+}
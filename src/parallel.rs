@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+use rayon::prelude::*;
+
+use crate::{Checkpoint, RopeSliceSource};
+
+/// Tokens lexed speculatively from the start of a piece of the text.
+struct Piece<T, E, X> {
+    tokens: Vec<(Result<T, E>, Range<usize>)>,
+    /// The lexer state before each token, followed by the state after the
+    /// last one.
+    states: Vec<Checkpoint<X>>,
+}
+
+impl<T, E, X: PartialEq> Piece<T, E, X> {
+    /// Returns the index of the token which the lexer would lex next from
+    /// `state`, if this piece has lexed it.
+    fn find(&self, state: &Checkpoint<X>) -> Option<usize> {
+        let index = self.states.partition_point(|s| s.offset < state.offset);
+        (self.states.get(index)? == state).then_some(index)
+    }
+}
+
+/// Lexes a rope slice on multiple threads, giving exactly the same tokens as
+/// lexing it sequentially.
+///
+/// The slice is split into about `pieces` pieces at chunk boundaries, and
+/// each piece is lexed in parallel as if a token started there, with default
+/// extras. The pieces are then stitched together in order: from the end of
+/// the previous piece, tokens are re-lexed sequentially until the lexer
+/// reaches the same offset and extras as one of the tokens lexed
+/// speculatively, from which point the rest of the piece is reused. Since
+/// most token boundaries are quickly found again, only a few tokens around
+/// each split are usually lexed twice.
+///
+/// Note that callbacks run during speculative lexing may see extras which a
+/// sequential lex would never produce, such as a nesting depth going below
+/// zero, and must not panic on them.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{lex_parallel, RopeSliceSource};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// enum Token {
+///     #[regex("[0-9]+")]
+///     Number,
+///     #[token("\n")]
+///     Newline,
+/// }
+///
+/// let rope = ropey::Rope::from_str(&"12345\n".repeat(100_000));
+/// let tokens = lex_parallel::<Token, _, _>(rope.slice(..), rayon::current_num_threads());
+///
+/// assert_eq!(tokens.len(), 200_000);
+/// assert_eq!(tokens[3], (Ok(Token::Newline), 11..12));
+/// ```
+pub fn lex_parallel<T, E, X>(
+    slice: ropey::RopeSlice<'_>,
+    pieces: usize,
+) -> Vec<(Result<T, E>, Range<usize>)>
+where
+    T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E, Extras = X> + Send,
+    E: Send,
+    X: Clone + Default + PartialEq + Send,
+{
+    let pieces: Vec<_> = split(slice, pieces)
+        .into_par_iter()
+        .map(|range| lex_piece::<T, E, X>(slice, range))
+        .collect();
+
+    let source = RopeSliceSource::new(slice);
+    let mut tokens = Vec::new();
+    let mut state = Checkpoint {
+        offset: 0,
+        extras: X::default(),
+    };
+    let mut lexer: Option<Lexer<T>> = None;
+
+    for piece in pieces {
+        loop {
+            if let Some(index) = piece.find(&state) {
+                tokens.extend(piece.tokens.into_iter().skip(index));
+                state = piece.states.last().unwrap().clone();
+                lexer = None;
+                break;
+            }
+
+            // The lexer has moved past the whole piece without agreeing with
+            // it, so it has to continue into the next one.
+            if state.offset >= piece.states.last().unwrap().offset {
+                break;
+            }
+
+            let lexer = lexer.get_or_insert_with(|| state.resume(&source));
+            let Some(token) = lexer.next() else {
+                return tokens;
+            };
+
+            tokens.push((token, lexer.span()));
+            state = Checkpoint {
+                offset: lexer.span().end,
+                extras: lexer.extras.clone(),
+            };
+        }
+    }
+
+    let lexer = lexer.get_or_insert_with(|| state.resume(&source));
+    while let Some(token) = lexer.next() {
+        tokens.push((token, lexer.span()));
+    }
+
+    tokens
+}
+
+/// Splits `slice` into about `pieces` byte ranges at chunk boundaries.
+fn split(slice: ropey::RopeSlice<'_>, pieces: usize) -> Vec<Range<usize>> {
+    let len = slice.len_bytes();
+    let mut starts: Vec<_> = (1..pieces)
+        .map(|i| {
+            let (_, start, _, _) = slice.chunk_at_byte(len * i / pieces);
+            start
+        })
+        .filter(|&start| start > 0)
+        .collect();
+    starts.dedup();
+
+    let ends = starts.iter().copied().chain([len]);
+    std::iter::once(0)
+        .chain(starts.iter().copied())
+        .zip(ends)
+        .map(|(start, end)| start..end)
+        .collect()
+}
+
+/// Lexes the tokens starting in `range`, starting from its start with
+/// default extras.
+fn lex_piece<T, E, X>(slice: ropey::RopeSlice<'_>, range: Range<usize>) -> Piece<T, E, X>
+where
+    T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E, Extras = X>,
+    X: Clone + Default,
+{
+    let source = RopeSliceSource::new(slice);
+    let mut lexer = Lexer::<T>::with_extras(&source, X::default());
+    lexer.bump(range.start);
+
+    let mut piece = Piece {
+        tokens: Vec::new(),
+        states: Vec::new(),
+    };
+
+    loop {
+        piece.states.push(Checkpoint {
+            offset: lexer.span().end,
+            extras: lexer.extras.clone(),
+        });
+
+        match lexer.next() {
+            Some(token) if lexer.span().start < range.end => {
+                piece.tokens.push((token, lexer.span()));
+            }
+            _ => return piece,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    #[logos(extras = usize)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[token("{", |lex| lex.extras += 1)]
+        Open,
+        // Speculative lexing can see more closing than opening braces.
+        #[token("}", |lex| lex.extras = lex.extras.saturating_sub(1))]
+        Close,
+        #[regex("[a-z]+", |lex| lex.extras)]
+        Word(usize),
+        #[regex(r#""[^"]*""#)]
+        String,
+    }
+
+    #[test]
+    fn test_matches_sequential() {
+        let text = r#"a { b "c } d" { e } "f
+            g" } h { i } "#
+            .repeat(2_000);
+        let rope = ropey::Rope::from_str(&text);
+        assert!(rope.chunks().count() > 10);
+
+        let expected: Vec<_> = Lexer::<Token>::new(&RopeSliceSource::from(&rope))
+            .spanned()
+            .collect();
+
+        for pieces in [1, 2, 3, 7, 50, 1_000] {
+            let tokens = lex_parallel::<Token, _, _>(rope.slice(..), pieces);
+            assert!(tokens == expected, "{pieces} pieces");
+        }
+    }
+}
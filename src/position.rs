@@ -0,0 +1,120 @@
+use std::ops::Range;
+
+use crate::{AsRopeSlice, ChunkedSource, ChunkedText};
+
+/// A line and column in a document, both counted from zero.
+///
+/// Columns are counted in chars. Lines are split wherever Ropey considers
+/// there to be a line break.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Converts a zero-based position to a one-based one, as usually shown
+    /// to users.
+    pub fn to_one_based(self) -> Self {
+        Self::new(self.line + 1, self.column + 1)
+    }
+
+    /// Creates a zero-based position from a one-based line and column, or
+    /// returns `None` if either is zero.
+    pub fn from_one_based(line: usize, column: usize) -> Option<Self> {
+        Some(Self::new(line.checked_sub(1)?, column.checked_sub(1)?))
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl<T: ChunkedText + AsRopeSlice> ChunkedSource<T> {
+    /// Returns the zero-based position of a byte offset, or `None` if it is
+    /// past the end of the text.
+    ///
+    /// Offsets inside a char are positioned at the start of that char.
+    ///
+    /// ```rust
+    /// # use logos_ropey::{Position, RopeSource};
+    /// let source = RopeSource::new("one\ntwo\n".into());
+    ///
+    /// assert_eq!(source.position(5), Some(Position::new(1, 1)));
+    /// assert_eq!(source.position(5).unwrap().to_one_based().to_string(), "2:2");
+    /// ```
+    pub fn position(&self, offset: usize) -> Option<Position> {
+        let slice = self.text().as_rope_slice();
+        let line = slice.try_byte_to_line(offset).ok()?;
+        let column = slice.byte_to_char(offset) - slice.line_to_char(line);
+        Some(Position::new(line, column))
+    }
+
+    /// Returns the zero-based positions of the ends of a span, or `None` if
+    /// it is out of bounds.
+    pub fn span_position(&self, span: Range<usize>) -> Option<Range<Position>> {
+        Some(self.position(span.start)?..self.position(span.end)?)
+    }
+
+    /// Returns the byte offset of a zero-based position, or `None` if it is
+    /// out of bounds.
+    ///
+    /// The column may point just past the last char of the line, including
+    /// its line break.
+    pub fn offset(&self, position: Position) -> Option<usize> {
+        let slice = self.text().as_rope_slice();
+        let line = slice.get_line(position.line)?;
+        if position.column > line.len_chars() {
+            return None;
+        }
+
+        let line_start = slice.line_to_char(position.line);
+        Some(slice.char_to_byte(line_start + position.column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RopeSliceSource;
+
+    #[test]
+    fn test_positions() {
+        let rope = ropey::Rope::from_str("aé\r\n€😀\n\nb");
+        let source = RopeSliceSource::from(&rope);
+
+        let positions = [
+            (0, Position::new(0, 0)),
+            (1, Position::new(0, 1)),
+            (3, Position::new(0, 2)),
+            (5, Position::new(1, 0)),
+            (8, Position::new(1, 1)),
+            (12, Position::new(1, 2)),
+            (13, Position::new(2, 0)),
+            (14, Position::new(3, 0)),
+            (15, Position::new(3, 1)),
+        ];
+
+        for (offset, position) in positions {
+            assert_eq!(source.position(offset), Some(position));
+            assert_eq!(source.offset(position), Some(offset));
+
+            let one_based = position.to_one_based();
+            assert_eq!(
+                Position::from_one_based(one_based.line, one_based.column),
+                Some(position)
+            );
+        }
+        assert_eq!(Position::from_one_based(0, 1), None);
+
+        assert_eq!(source.position(16), None);
+        assert_eq!(source.offset(Position::new(3, 2)), None);
+        assert_eq!(source.offset(Position::new(4, 0)), None);
+    }
+}
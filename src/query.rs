@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+
+use crate::chunked::lexer_at_start;
+use crate::{Checkpoints, LexStart};
+
+/// A token, its span and its neighbours, as found by [`token_at`] or
+/// [`lex_token_at`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenAt<R> {
+    pub previous: Option<(R, Range<usize>)>,
+    pub token: R,
+    pub span: Range<usize>,
+    pub next: Option<(R, Range<usize>)>,
+}
+
+/// Returns the index of the token whose span contains the byte `offset` in
+/// a list of spanned tokens, or `None` if there is no such token.
+pub fn find_token<R>(tokens: &[(R, Range<usize>)], offset: usize) -> Option<usize> {
+    let index = tokens.partition_point(|(_, span)| span.end <= offset);
+    let (_, span) = tokens.get(index)?;
+    (span.start <= offset).then_some(index)
+}
+
+/// Returns the token whose span contains the byte `offset` in a list of
+/// spanned tokens, and its neighbours.
+///
+/// Returns `None` if `offset` is not in any token, such as when it is in
+/// skipped whitespace. Char offsets into a rope can be converted with
+/// [`ropey::Rope::char_to_byte`].
+pub fn token_at<R>(tokens: &[(R, Range<usize>)], offset: usize) -> Option<TokenAt<&R>> {
+    let index = find_token(tokens, offset)?;
+    let neighbour = |i: usize| tokens.get(i).map(|(token, span)| (token, span.clone()));
+
+    Some(TokenAt {
+        previous: index.checked_sub(1).and_then(neighbour),
+        token: &tokens[index].0,
+        span: tokens[index].1.clone(),
+        next: neighbour(index + 1),
+    })
+}
+
+/// Lexes the token whose span contains the byte `offset`, and its
+/// neighbours, without a cached token list.
+///
+/// Lexing starts from the last of `checkpoints` before `offset`, or from the
+/// [`LexStart::lex_start`] of the source if there are none, so only the
+/// tokens between that checkpoint and `offset` are lexed.
+///
+/// Returns `None` if `offset` is not in any token, such as when it is in
+/// skipped whitespace.
+///
+#[cfg_attr(feature = "ropey1", doc = "```rust")]
+#[cfg_attr(not(feature = "ropey1"), doc = "```ignore")]
+/// # use logos::Logos;
+/// # use logos_ropey::{lex_token_at, Checkpoints, RopeSliceSource};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+///     #[regex("[0-9]+")]
+///     Number,
+/// }
+///
+/// let rope = ropey::Rope::from_str("one 2 three");
+/// let source = RopeSliceSource::from(&rope);
+///
+/// let mut checkpoints = Checkpoints::new(100);
+/// let _: Vec<_> = checkpoints.record(Token::lexer(&source)).collect();
+///
+/// let token = lex_token_at::<Token>(&source, &checkpoints, 4).unwrap();
+/// assert_eq!(token.token, Ok(Token::Number));
+/// assert_eq!(token.span, 4..5);
+/// assert_eq!(token.previous, Some((Ok(Token::Word), 0..3)));
+/// assert_eq!(token.next, Some((Ok(Token::Word), 6..11)));
+/// ```
+pub fn lex_token_at<'s, T>(
+    source: &'s T::Source,
+    checkpoints: &Checkpoints<T::Extras>,
+    offset: usize,
+) -> Option<TokenAt<Result<T, T::Error>>>
+where
+    T: Logos<'s>,
+    T::Source: LexStart,
+    T::Extras: Clone + Default,
+{
+    let Some(checkpoint) = checkpoints.before(offset) else {
+        return lex_from(lexer_at_start(source), offset);
+    };
+
+    let found = lex_from(checkpoint.resume(source), offset)?;
+    if found.previous.is_some() || checkpoint.offset <= source.lex_start() {
+        return Some(found);
+    }
+
+    // The token starts at the checkpoint, so the previous token has to be
+    // lexed from an earlier one.
+    match checkpoints.before(checkpoint.offset - 1) {
+        Some(earlier) => lex_from(earlier.resume(source), offset),
+        None => lex_from(lexer_at_start(source), offset),
+    }
+}
+
+fn lex_from<'s, T: Logos<'s>>(
+    mut lexer: Lexer<'s, T>,
+    offset: usize,
+) -> Option<TokenAt<Result<T, T::Error>>> {
+    let mut previous = None;
+
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+
+        if span.end > offset {
+            if span.start > offset {
+                return None;
+            }
+
+            let next = lexer.next().map(|token| (token, lexer.span()));
+            return Some(TokenAt {
+                previous,
+                token,
+                span,
+                next,
+            });
+        }
+
+        previous = Some((token, span));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[regex("[a-z]+")]
+        Ident,
+        #[regex("[0-9]+")]
+        Number,
+        #[token("=")]
+        Eq,
+        #[token(";")]
+        Semi,
+    }
+
+    #[test]
+    fn test_lex_matches_cached() {
+        let source = "abc = 1;\n  de = 23;\nf=4;".repeat(3);
+        let tokens: Vec<_> = Token::lexer(&source).spanned().collect();
+
+        for interval in [1, 2, 5, 100] {
+            let mut checkpoints = Checkpoints::new(interval);
+            let _: Vec<_> = checkpoints.record(Token::lexer(&source)).collect();
+
+            for offset in 0..=source.len() {
+                let lexed = lex_token_at::<Token>(&source, &checkpoints, offset);
+                let cached = token_at(&tokens, offset);
+
+                // The cached token is a reference, which prints the same.
+                assert_eq!(
+                    format!("{lexed:?}"),
+                    format!("{cached:?}"),
+                    "offset {offset}, interval {interval}",
+                );
+            }
+        }
+    }
+}
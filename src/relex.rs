@@ -0,0 +1,267 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+
+use crate::{Edit, LexStart};
+
+/// Updates the tokens of a document after an edit, only re-lexing around the
+/// edited range.
+///
+/// `tokens` must be the spanned output of lexing the document before the
+/// edit, and `source` the document after it. Lexing restarts from the token
+/// before the first one which ends at or after the start of the edit, since
+/// that token's match may have depended on the bytes following it. It stops
+/// as soon as it produces a token which is equal to an old token after the
+/// edit, with its span shifted accordingly. The remaining old tokens are then
+/// kept with shifted spans.
+///
+/// Returns the range of indices in `tokens` which were re-lexed.
+///
+/// If the edit is before the second token, lexing restarts from the
+/// [`LexStart::lex_start`] of `source`, such as the start of a
+/// [window](crate::ChunkedSource::window).
+///
+/// Lexing restarts with default extras, so this only gives the same result
+/// as a full lex if the lexer's behaviour doesn't depend on state carried in
+/// its extras. It also assumes that a token's match only depends on the bytes
+/// up to the start of the next token. Grammars which backtrack further, such
+/// as when a longer pattern fails to match, should use
+/// [`relex_with_lookahead`] instead.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{relex, Edit};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+/// }
+///
+/// let mut tokens: Vec<_> = Token::lexer("one two three").spanned().collect();
+///
+/// // Replace "two" with "four".
+/// let relexed = relex::<Token>("one four three", &mut tokens, &Edit::new(4..7, 4));
+///
+/// assert_eq!(relexed, 0..2);
+/// assert_eq!(
+///     tokens,
+///     [(Ok(Token::Word), 0..3), (Ok(Token::Word), 4..8), (Ok(Token::Word), 9..14)],
+/// );
+/// ```
+pub fn relex<'s, T>(
+    source: &'s T::Source,
+    tokens: &mut Vec<(Result<T, T::Error>, Range<usize>)>,
+    edit: &Edit,
+) -> Range<usize>
+where
+    T: Logos<'s> + PartialEq,
+    T::Source: LexStart,
+    T::Extras: Default,
+{
+    relex_with_lookahead(source, tokens, edit, 0)
+}
+
+/// Like [`relex`], but for grammars which may read up to `lookahead` bytes
+/// past the end of a token before backtracking to it.
+///
+/// Such a token could have matched differently if any of those bytes
+/// changed, so lexing restarts from the token before the first one which
+/// ends at most `lookahead` bytes before the start of the edit.
+///
+/// For example, with patterns for numbers, dots and versions like `1.2.3`,
+/// lexing `1.2.` reads up to the end of the text looking for a version, then
+/// backtracks to the number `1`. Appending `3` then turns the whole text into
+/// a version, which `relex` would miss as it only restarts from `2`:
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{relex_with_lookahead, Edit};
+/// #[derive(Logos, Debug, PartialEq)]
+/// enum Token {
+///     #[regex("[0-9]+")]
+///     Number,
+///     #[token(".")]
+///     Dot,
+///     #[regex(r"[0-9]+\.[0-9]+\.[0-9]+")]
+///     Version,
+/// }
+///
+/// let mut tokens: Vec<_> = Token::lexer("1.2.").spanned().collect();
+/// assert_eq!(tokens.len(), 4);
+///
+/// relex_with_lookahead::<Token>("1.2.3", &mut tokens, &Edit::new(4..4, 1), 3);
+/// assert_eq!(tokens, [(Ok(Token::Version), 0..5)]);
+/// ```
+pub fn relex_with_lookahead<'s, T>(
+    source: &'s T::Source,
+    tokens: &mut Vec<(Result<T, T::Error>, Range<usize>)>,
+    edit: &Edit,
+    lookahead: usize,
+) -> Range<usize>
+where
+    T: Logos<'s> + PartialEq,
+    T::Source: LexStart,
+    T::Extras: Default,
+{
+    let first = tokens.partition_point(|(_, span)| span.end + lookahead < edit.range.start);
+    let (restart, start) = match first.checked_sub(1) {
+        Some(i) => (i, tokens[i].1.start),
+        None => (0, source.lex_start()),
+    };
+
+    // The first old token which lies entirely after the edit.
+    let mut old = first + tokens[first..].partition_point(|(_, span)| span.start < edit.range.end);
+
+    let mut lexer = Lexer::<T>::new(source);
+    lexer.bump(start);
+
+    let mut relexed = Vec::new();
+    let mut synced = false;
+
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+
+        if span.start >= edit.new_range().end {
+            while old < tokens.len() && edit.shift(tokens[old].1.start) < span.start {
+                old += 1;
+            }
+
+            if let Some((old_token, old_span)) = tokens.get(old) {
+                if edit.shift(old_span.start) == span.start
+                    && edit.shift(old_span.end) == span.end
+                    && *old_token == token
+                {
+                    synced = true;
+                    break;
+                }
+            }
+        }
+
+        relexed.push((token, span));
+    }
+
+    if !synced {
+        old = tokens.len();
+    }
+
+    for (_, span) in &mut tokens[old..] {
+        *span = edit.shift(span.start)..edit.shift(span.end);
+    }
+
+    let len = relexed.len();
+    tokens.splice(restart..old, relexed);
+    restart..restart + len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[regex("[a-z]+")]
+        Ident,
+        #[regex("[0-9]+")]
+        Number,
+        #[token("=")]
+        Eq,
+        #[token(";")]
+        Semi,
+        #[regex(r#""[^"]*""#)]
+        String,
+    }
+
+    /// Re-lexes `text` after replacing `range` with `replacement`, and checks
+    /// the result against a full lex, returning the re-lexed token indices.
+    fn check(text: &str, range: Range<usize>, replacement: &str) -> Range<usize> {
+        let mut tokens: Vec<_> = Token::lexer(text).spanned().collect();
+
+        let mut new_text = text.to_owned();
+        new_text.replace_range(range.clone(), replacement);
+        let edit = Edit::new(range, replacement.len());
+
+        let relexed = relex::<Token>(&new_text, &mut tokens, &edit);
+        let expected: Vec<_> = Token::lexer(&new_text).spanned().collect();
+        assert_eq!(tokens, expected, "{new_text:?}");
+        relexed
+    }
+
+    #[test]
+    fn test_relex() {
+        let text = "a = 1;\nb = 2;\nc = 3;\nd = 4;\n";
+
+        // Changing a token only re-lexes it and its predecessor.
+        assert_eq!(check(text, 11..12, "42"), 5..7);
+        // Inserting tokens.
+        assert_eq!(check(text, 13..13, " e = 5;"), 6..12);
+        // Deleting tokens.
+        assert_eq!(check(text, 7..14, ""), 3..4);
+        // Splitting a token.
+        assert_eq!(check(text, 15..15, "x "), 7..9);
+        // Editing before the first token.
+        assert_eq!(check(text, 0..0, "z "), 0..1);
+        // Opening a string swallows the rest of the document.
+        check(text, 4..4, "\"");
+        // Editing at the end.
+        check(text, text.len()..text.len(), "e");
+    }
+
+    #[test]
+    fn test_relex_backtracking() {
+        #[derive(Logos, Debug, PartialEq)]
+        enum Token {
+            #[regex("[0-9]+")]
+            Number,
+            #[token(".")]
+            Dot,
+            #[regex(r"[0-9]+\.[0-9]+\.[0-9]+")]
+            Version,
+        }
+
+        let old: Vec<_> = Token::lexer("1.2.").spanned().collect();
+        let expected: Vec<_> = Token::lexer("1.2.3").spanned().collect();
+        assert_eq!(expected, [(Ok(Token::Version), 0..5)]);
+
+        // Appending "3" makes a version of tokens which the lexer backtracked
+        // over, so restarting from the token before the edit isn't enough.
+        let edit = Edit::new(4..4, 1);
+        let mut tokens = old.clone();
+        relex::<Token>("1.2.3", &mut tokens, &edit);
+        assert_ne!(tokens, expected);
+
+        let mut tokens = old;
+        assert_eq!(
+            relex_with_lookahead::<Token>("1.2.3", &mut tokens, &edit, 3),
+            0..1
+        );
+        assert_eq!(tokens, expected);
+    }
+
+    #[cfg(feature = "ropey1")]
+    #[test]
+    fn test_relex_window() {
+        use crate::RopeSliceSource;
+
+        #[derive(Logos, Debug, PartialEq)]
+        #[logos(source = RopeSliceSource<'s>)]
+        #[logos(skip " ")]
+        enum Word {
+            #[regex("[a-z]+")]
+            Word,
+        }
+
+        let rope = ropey::Rope::from_str("// one two");
+        let source = RopeSliceSource::window(rope.slice(..), 3..10).unwrap();
+        let mut tokens: Vec<_> = source.lexer::<Word>().spanned().collect();
+
+        // Replace "one" with "ones", which is the first token in the window.
+        let rope = ropey::Rope::from_str("// ones two");
+        let source = RopeSliceSource::window(rope.slice(..), 3..11).unwrap();
+        let relexed = relex::<Word>(&source, &mut tokens, &Edit::new(3..6, 4));
+
+        assert_eq!(relexed, 0..1);
+        assert_eq!(tokens, [(Ok(Word::Word), 3..7), (Ok(Word::Word), 8..11)]);
+    }
+}
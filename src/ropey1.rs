@@ -0,0 +1,381 @@
+use std::ops::Range;
+
+use crate::chunked::{ChunkedSource, ChunkedText};
+
+/// A [`logos::Source`] which wraps a [`ropey::RopeSlice`].
+///
+/// To use it, set the `source` attribute on your `logos` derive to
+/// `RopeSliceSource<'s>`:
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::RopeSliceSource;
+/// #[derive(Logos)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// enum Token {
+///     #[regex(".")]
+///     Token,
+/// }
+/// ```
+pub type RopeSliceSource<'s> = ChunkedSource<ropey::RopeSlice<'s>>;
+
+/// A [`logos::Source`] which owns a [`ropey::Rope`].
+///
+/// Unlike [`RopeSliceSource`], a `RopeSource` isn't tied to a borrow of the
+/// rope, so the source itself can be stored in a struct or moved to another
+/// thread. Lexers still borrow their source, as with any other source, but
+/// they can be sent to another thread for as long as the source is borrowed.
+/// Ropes are cheap to clone, so taking a snapshot of a document being edited
+/// costs very little:
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::RopeSource;
+/// #[derive(Logos)]
+/// #[logos(source = RopeSource)]
+/// enum Token {
+///     #[regex(".")]
+///     Token,
+/// }
+///
+/// let rope = ropey::Rope::from_str("abc");
+/// let source = RopeSource::new(rope.clone());
+///
+/// // Move the source to another thread.
+/// let count = std::thread::spawn(move || Token::lexer(&source).count())
+///     .join()
+///     .unwrap();
+/// assert_eq!(count, 3);
+///
+/// // Send a lexer to another thread, which must finish while the source is
+/// // still borrowed.
+/// let source = RopeSource::new(rope);
+/// let mut lexer = Token::lexer(&source);
+/// lexer.next();
+/// let rest = std::thread::scope(|scope| {
+///     scope.spawn(move || lexer.count()).join().unwrap()
+/// });
+/// assert_eq!(rest, 2);
+/// ```
+pub type RopeSource = ChunkedSource<ropey::Rope>;
+
+/// Text which can be viewed as a [`ropey::RopeSlice`].
+///
+/// Sources over such text provide extra methods, such as
+/// [`ChunkedSource::position`].
+pub trait AsRopeSlice {
+    fn as_rope_slice(&self) -> ropey::RopeSlice<'_>;
+}
+
+impl<'s> AsRopeSlice for ropey::RopeSlice<'s> {
+    fn as_rope_slice(&self) -> ropey::RopeSlice<'_> {
+        *self
+    }
+}
+
+impl AsRopeSlice for ropey::Rope {
+    fn as_rope_slice(&self) -> ropey::RopeSlice<'_> {
+        self.slice(..)
+    }
+}
+
+impl<'s> From<&'s ropey::Rope> for RopeSliceSource<'s> {
+    fn from(value: &'s ropey::Rope) -> Self {
+        Self::new(value.slice(..))
+    }
+}
+
+// SAFETY: Ropey stores chunks in separate reference-counted leaves.
+unsafe impl<'s> ChunkedText for ropey::RopeSlice<'s> {
+    type Slice<'a> = ropey::RopeSlice<'a> where 's: 'a;
+
+    fn len_bytes(&self) -> usize {
+        ropey::RopeSlice::len_bytes(self)
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        let (chunk, start, _, _) = self.chunk_at_byte(offset);
+        (chunk, start)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        self.get_byte_slice(range)
+    }
+}
+
+// SAFETY: Ropey stores chunks in separate reference-counted leaves.
+unsafe impl ChunkedText for ropey::Rope {
+    type Slice<'a> = ropey::RopeSlice<'a>;
+
+    fn len_bytes(&self) -> usize {
+        ropey::Rope::len_bytes(self)
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        let (chunk, start, _, _) = self.chunk_at_byte(offset);
+        (chunk, start)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        self.get_byte_slice(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::test_util::chunked_rope;
+
+    #[derive(logos::Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    enum Token {
+        #[regex(r"[^,]*,")]
+        Token,
+    }
+
+    #[test]
+    fn test_source() {
+        let mut rope = ropey::Rope::new();
+
+        // Build a sufficiently large string that we exercise chunking.
+        for len in 1..=1_000 {
+            let mut token = str::repeat("x", len);
+            token.push_str(",");
+            rope.append(token.into());
+        }
+
+        // Make sure we have chunks.
+        assert!(rope.chunks().count() > 10);
+
+        let source = RopeSliceSource::new(rope.slice(..));
+        let lexer = logos::Lexer::new(&source);
+
+        assert_eq!(
+            lexer
+                .inspect(|t| assert_eq!(t.as_ref().ok(), Some(&Token::Token)))
+                .count(),
+            1_000
+        );
+    }
+
+    #[derive(logos::Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    enum Keyword {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[token("return")]
+        Return,
+        #[regex(r"[ \n]+")]
+        Space,
+    }
+
+    #[derive(logos::Logos, Debug, PartialEq)]
+    enum StrKeyword {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[token("return")]
+        Return,
+        #[regex(r"[ \n]+")]
+        Space,
+    }
+
+    #[test]
+    fn test_read_across_chunks() {
+        let text = "function functional return\n".repeat(100);
+        let expected: Vec<_> = logos::Lexer::<StrKeyword>::new(&text)
+            .spanned()
+            .map(|(t, span)| (format!("{t:?}"), span))
+            .collect();
+
+        for chunk_len in 1..=12 {
+            let rope = chunked_rope(&text, chunk_len);
+            let source = RopeSliceSource::from(&rope);
+            let actual: Vec<_> = logos::Lexer::<Keyword>::new(&source)
+                .spanned()
+                .map(|(t, span)| (format!("{t:?}"), span))
+                .collect();
+
+            assert_eq!(actual, expected, "chunk length {chunk_len}");
+        }
+    }
+
+    #[test]
+    fn test_read_out_of_order() {
+        let text = "abcdefghijklmnopqrstuvwxyz".repeat(10);
+        let rope = chunked_rope(&text, 7);
+        let source = RopeSliceSource::from(&rope);
+
+        for offset in (0..text.len()).rev().step_by(3) {
+            assert_eq!(
+                logos::Source::read::<u8>(&source, offset),
+                Some(text.as_bytes()[offset])
+            );
+        }
+
+        assert_eq!(logos::Source::read::<u8>(&source, text.len()), None);
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let text = "aé€😀b".repeat(5);
+        let rope = ropey::Rope::from_str(&text);
+        let source = RopeSliceSource::from(&rope);
+
+        for index in 0..=text.len() + 1 {
+            let expected = (index..=text.len())
+                .find(|&i| text.is_char_boundary(i))
+                .unwrap_or(text.len());
+            assert_eq!(logos::Source::find_boundary(&source, index), expected);
+            assert_eq!(
+                logos::Source::is_boundary(&source, index),
+                text.is_char_boundary(index),
+                "index {index}"
+            );
+        }
+    }
+
+    #[derive(logos::Logos, Debug, PartialEq)]
+    #[logos(source = RopeSource)]
+    enum OwnedToken {
+        #[regex(r"[^,]*,")]
+        Token,
+    }
+
+    #[test]
+    fn test_owned_source() {
+        let mut rope = ropey::Rope::new();
+
+        for len in 1..=1_000 {
+            let mut token = str::repeat("x", len);
+            token.push_str(",");
+            rope.append(token.into());
+        }
+
+        let source = RopeSource::new(rope);
+        let lexer = std::thread::spawn(move || {
+            logos::Lexer::<OwnedToken>::new(&source)
+                .spanned()
+                .map(|(t, span)| (t, span.len()))
+                .collect::<Vec<_>>()
+        });
+
+        let tokens = lexer.join().unwrap();
+        assert_eq!(tokens.len(), 1_000);
+        for (len, (token, span_len)) in (1..).zip(tokens) {
+            assert_eq!(token, Ok(OwnedToken::Token));
+            assert_eq!(span_len, len + 1);
+        }
+    }
+
+    #[test]
+    fn test_send_lexer() {
+        let source = RopeSource::new(chunked_rope(&"a,bb,ccc,".repeat(100), 7));
+        let mut lexer = logos::Lexer::<OwnedToken>::new(&source);
+        assert_eq!(lexer.next(), Some(Ok(OwnedToken::Token)));
+
+        // Move the partly consumed lexer to another thread.
+        let spans = std::thread::scope(|scope| {
+            scope
+                .spawn(move || lexer.spanned().map(|(_, span)| span).collect::<Vec<_>>())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(spans.len(), 299);
+        assert_eq!(spans[0], 2..5);
+        assert_eq!(spans.last(), Some(&(896..900)));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        fn assert_send<T: Send>() {}
+
+        assert_send_sync::<RopeSliceSource<'static>>();
+        assert_send_sync::<RopeSource>();
+        assert_send::<logos::Lexer<'static, Token>>();
+        assert_send::<logos::Lexer<'static, OwnedToken>>();
+    }
+
+    #[test]
+    fn test_shared_source() {
+        let text = "a,bb,ccc,".repeat(100);
+        let rope = chunked_rope(&text, 7);
+        let source = RopeSliceSource::new(rope.slice(..));
+
+        let lex = || {
+            logos::Lexer::<Token>::new(&source)
+                .spanned()
+                .collect::<Vec<_>>()
+        };
+        let expected = lex();
+        assert_eq!(expected.len(), 300);
+
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4).map(|_| scope.spawn(lex)).collect();
+            for thread in threads {
+                assert_eq!(thread.join().unwrap(), expected);
+            }
+        });
+    }
+
+    #[test]
+    fn test_window() {
+        let text = "function functional return\n".repeat(10);
+        let rope = chunked_rope(&text, 5);
+
+        for window in [0..text.len(), 9..27, 30..100, 27..27] {
+            let offset = window.start;
+            let expected: Vec<_> = logos::Lexer::<StrKeyword>::new(&text[window.clone()])
+                .spanned()
+                .map(|(t, span)| (format!("{t:?}"), span.start + offset..span.end + offset))
+                .collect();
+
+            let source = RopeSliceSource::window(rope.slice(..), window.clone()).unwrap();
+            let actual: Vec<_> = source
+                .lexer::<Keyword>()
+                .spanned()
+                .map(|(t, span)| (format!("{t:?}"), span))
+                .collect();
+
+            assert_eq!(actual, expected, "window {window:?}");
+            if offset > 0 {
+                assert_eq!(logos::Source::slice(&source, 0..window.end), None);
+            }
+        }
+
+        assert!(RopeSliceSource::window(rope.slice(..), 0..text.len() + 1).is_none());
+    }
+
+    #[test]
+    fn test_slice_str() {
+        let text = "aé€😀b".repeat(5);
+        let rope = chunked_rope(&text, 11);
+        let source = RopeSliceSource::from(&rope);
+
+        for start in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+            for end in (start..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+                let slice = source.slice_str(start..end).unwrap();
+                assert_eq!(slice, &text[start..end]);
+
+                let contiguous = end - start <= 1 || start / 11 == (end - 1) / 11;
+                assert_eq!(matches!(slice, Cow::Borrowed(_)), contiguous, "{start}..{end}");
+            }
+        }
+
+        assert_eq!(source.slice_str(0..2), None);
+        assert_eq!(source.slice_str(0..text.len() + 1), None);
+        let (start, end) = (3, 1);
+        assert_eq!(source.slice_str(start..end), None);
+
+        let window = RopeSliceSource::window(rope.slice(..), 11..22).unwrap();
+        assert_eq!(window.slice_str(11..22).unwrap(), &text[11..22]);
+        assert_eq!(window.slice_str(10..22), None);
+        assert_eq!(window.slice_str(11..23), None);
+    }
+}
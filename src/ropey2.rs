@@ -0,0 +1,96 @@
+//! Sources over [Ropey 2](ropey2) ropes, available with the `ropey2` feature.
+//!
+//! These behave exactly like the crate's Ropey 1 sources, so the two can be
+//! used side by side while migrating:
+//!
+//! ```rust
+//! # use logos::Logos;
+//! # use logos_ropey::ropey2::RopeSliceSource;
+//! #[derive(Logos)]
+//! #[logos(source = RopeSliceSource<'s>)]
+//! enum Token {
+//!     #[regex(".")]
+//!     Token,
+//! }
+//! ```
+
+use std::ops::Range;
+
+use crate::chunked::{ChunkedSource, ChunkedText};
+
+/// A [`logos::Source`] which wraps a [`ropey2::RopeSlice`].
+pub type RopeSliceSource<'s> = ChunkedSource<ropey2::RopeSlice<'s>>;
+
+/// A [`logos::Source`] which owns a [`ropey2::Rope`].
+pub type RopeSource = ChunkedSource<ropey2::Rope>;
+
+impl<'s> From<&'s ropey2::Rope> for RopeSliceSource<'s> {
+    fn from(value: &'s ropey2::Rope) -> Self {
+        Self::new(value.slice(..))
+    }
+}
+
+// SAFETY: Ropey stores chunks in separate reference-counted leaves.
+unsafe impl<'s> ChunkedText for ropey2::RopeSlice<'s> {
+    type Slice<'a> = ropey2::RopeSlice<'a> where 's: 'a;
+
+    fn len_bytes(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        self.chunk(offset)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        self.try_slice(range).ok()
+    }
+}
+
+// SAFETY: Ropey stores chunks in separate reference-counted leaves.
+unsafe impl ChunkedText for ropey2::Rope {
+    type Slice<'a> = ropey2::RopeSlice<'a>;
+
+    fn len_bytes(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        self.chunk(offset)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        self.try_slice(range).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked::tests::{assert_lexes_like_str, edits, texts};
+
+    #[derive(logos::Logos, Debug)]
+    #[logos(source = RopeSliceSource<'s>)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    #[test]
+    fn test_lexes_like_str() {
+        for text in texts() {
+            let mut rope = ropey2::Rope::from_str(&text);
+            for range in edits(&text) {
+                rope.remove(range.clone());
+                rope.insert(range.start, &text[range]);
+            }
+
+            assert_lexes_like_str::<Token>(&RopeSliceSource::from(&rope), &text);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+/// Minimum number of bytes copied into a new stitch buffer.
+///
+/// Reads near a chunk boundary tend to come in runs at increasing offsets, so
+/// copying a little more than requested lets the following reads share it.
+const WINDOW: usize = 64;
+
+/// Contiguous copies of byte ranges which straddle chunk boundaries.
+///
+/// Buffers are only ever added while the `Stitch` is shared, never modified
+/// or dropped, so a pointer returned by [`Stitch::get`] remains valid for as
+/// long as the `Stitch` itself is borrowed. This means the memory used grows
+/// with the number of chunk boundaries which have been read across. The
+/// buffers are behind a lock so that lexers on several threads can share a
+/// source, but it is only taken for reads which straddle a boundary.
+#[derive(Default)]
+pub(crate) struct Stitch {
+    /// Buffers keyed by their `(start, end)` byte range in the source.
+    buffers: Mutex<BTreeMap<(usize, usize), Vec<u8>>>,
+}
+
+impl Stitch {
+    /// Returns a pointer to `size` contiguous bytes starting at `offset`.
+    ///
+    /// If no existing buffer covers the range, a new one of at most `len -
+    /// offset` bytes is allocated and `fill` is called to copy the source
+    /// bytes starting at `offset` into it. `offset + size` must not exceed
+    /// `len`.
+    pub(crate) fn get(
+        &self,
+        offset: usize,
+        size: usize,
+        len: usize,
+        fill: impl FnOnce(&mut [u8]),
+    ) -> *const u8 {
+        debug_assert!(offset + size <= len);
+
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((&(start, end), buffer)) = buffers.range(..=(offset, usize::MAX)).next_back() {
+            if offset + size <= end {
+                return buffer[offset - start..].as_ptr();
+            }
+        }
+
+        let mut buffer = vec![0; size.max(WINDOW).min(len - offset)];
+        fill(&mut buffer);
+
+        // The heap allocation doesn't move when the `Vec` is moved into the map.
+        let ptr = buffer.as_ptr();
+        let key = (offset, offset + buffer.len());
+        let previous = buffers.insert(key, buffer);
+        // Any buffer with this key would have covered the read above.
+        debug_assert!(previous.is_none());
+        ptr
+    }
+}
@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+
+use crate::{relex, Edit, RopeSliceSource};
+
+/// A [`ropey::Rope`] together with its tokens, which are kept up to date as
+/// the rope is edited.
+///
+/// Edits only re-lex the tokens around the edited range, as described in
+/// [`relex`], and shift the spans of the tokens after it.
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{RopeSliceSource, TokenStore};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+///     #[regex("[0-9]+")]
+///     Number,
+/// }
+///
+/// let mut store = TokenStore::<Token>::new("one two three".into());
+/// store.apply_edit(4..7, "2");
+///
+/// assert_eq!(store.rope().to_string(), "one 2 three");
+/// assert_eq!(
+///     store.tokens(),
+///     [(Ok(Token::Word), 0..3), (Ok(Token::Number), 4..5), (Ok(Token::Word), 6..11)],
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenStore<T, E = ()> {
+    rope: ropey::Rope,
+    tokens: Vec<Spanned<T, E>>,
+}
+
+/// A token or error and its span, as produced by [`logos::Lexer::spanned`].
+pub type Spanned<T, E = ()> = (Result<T, E>, Range<usize>);
+
+impl<T, E> TokenStore<T, E>
+where
+    T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E> + PartialEq,
+    for<'s> <T as Logos<'s>>::Extras: Default,
+{
+    /// Lexes all of `rope`.
+    pub fn new(rope: ropey::Rope) -> Self {
+        let tokens = Lexer::<T>::new(&RopeSliceSource::from(&rope))
+            .spanned()
+            .collect();
+        Self { rope, tokens }
+    }
+
+    pub fn rope(&self) -> &ropey::Rope {
+        &self.rope
+    }
+
+    /// The spanned tokens of the rope.
+    pub fn tokens(&self) -> &[Spanned<T, E>] {
+        &self.tokens
+    }
+
+    pub fn into_parts(self) -> (ropey::Rope, Vec<Spanned<T, E>>) {
+        (self.rope, self.tokens)
+    }
+
+    /// Replaces the byte range `range` of the rope with `text` and updates
+    /// the tokens.
+    ///
+    /// Returns the range of indices in [`TokenStore::tokens`] which were
+    /// re-lexed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or not on char boundaries.
+    pub fn apply_edit(&mut self, range: Range<usize>, text: &str) -> Range<usize> {
+        let start = self.rope.byte_to_char(range.start);
+        let end = self.rope.byte_to_char(range.end);
+        assert!(
+            self.rope.char_to_byte(start) == range.start
+                && self.rope.char_to_byte(end) == range.end,
+            "edit range is not on char boundaries",
+        );
+
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+
+        let edit = Edit::new(range, text.len());
+        relex::<T>(&RopeSliceSource::from(&self.rope), &mut self.tokens, &edit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[regex("[a-zé]+")]
+        Ident,
+        #[regex("[0-9]+")]
+        Number,
+        #[token("=")]
+        Eq,
+        #[token(";")]
+        Semi,
+    }
+
+    #[test]
+    fn test_apply_edit() {
+        let mut store = TokenStore::<Token>::new("a = 1;\nb = 2;\n".repeat(500).into());
+
+        let edits = [
+            (7..8, "café"),
+            (0..0, "x = 0;\n"),
+            (20..40, ""),
+            (3..3, "12 "),
+        ];
+        for (range, text) in edits {
+            store.apply_edit(range, text);
+
+            let expected: Vec<_> = Lexer::<Token>::new(&RopeSliceSource::from(store.rope()))
+                .spanned()
+                .collect();
+            assert_eq!(store.tokens(), expected);
+        }
+    }
+}
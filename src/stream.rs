@@ -0,0 +1,280 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos, Source};
+
+use crate::{Checkpoint, ChunkedSource, ChunkedText, LexStart};
+
+/// The result of [`StreamLexer::next_token`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Streamed<R> {
+    /// A complete token.
+    Token(R, Range<usize>),
+    /// The rest of the input may be the start of a longer token, so lexing
+    /// has to wait until more input has been appended, or
+    /// [`StreamLexer::finish`] has been called.
+    NeedMoreInput,
+    /// The input is finished and has been lexed completely.
+    Done,
+}
+
+/// Lexes input which is still arriving, such as a growing log file read
+/// into a rope.
+///
+/// A token which ends at the end of the input may become longer, or stop
+/// being an error, once more input arrives, so it is only returned once more
+/// input has been appended or the input is finished. In the meantime,
+/// [`StreamLexer::next_token`] returns [`Streamed::NeedMoreInput`], and the
+/// next call starts again from the end of the last complete token.
+///
+/// The stream lexer only keeps the offset and extras of the lexer, so the
+/// source can be recreated from the appended rope between calls:
+///
+#[cfg_attr(feature = "ropey1", doc = "```rust")]
+#[cfg_attr(not(feature = "ropey1"), doc = "```ignore")]
+/// # use logos::Logos;
+/// # use logos_ropey::{RopeSliceSource, StreamLexer, Streamed};
+/// #[derive(Logos, Debug, PartialEq)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+/// }
+///
+/// let mut rope = ropey::Rope::new();
+/// let mut stream = StreamLexer::new();
+/// let mut tokens = Vec::new();
+///
+/// for input in ["one tw", "o thr", "ee", ""] {
+///     if input.is_empty() {
+///         stream.finish();
+///     }
+///     rope.append(input.into());
+///
+///     let mut source = RopeSliceSource::from(&rope);
+///     while let Streamed::Token(token, span) = stream.next_token::<Token, _>(&mut source) {
+///         tokens.push((token, span));
+///     }
+/// }
+///
+/// assert_eq!(
+///     tokens,
+///     [(Ok(Token::Word), 0..3), (Ok(Token::Word), 4..7), (Ok(Token::Word), 8..13)],
+/// );
+/// ```
+///
+/// A token is also held back if the lexer tried to read past the end of the
+/// input while matching it, even if it then backtracked to an earlier end.
+/// This covers an unclosed string, which is an error until its closing quote
+/// arrives, or `ab` in `abc` with the patterns `ab|abcd`. The lexer may also
+/// read ahead several bytes at a time, so tokens near the end of the input
+/// can be held back even if they are complete. Streaming therefore needs a
+/// [`ChunkedSource`], which records such reads.
+#[derive(Clone, Debug)]
+pub struct StreamLexer<X> {
+    /// The state after the last complete token.
+    checkpoint: Checkpoint<X>,
+    finished: bool,
+}
+
+impl<X: Clone + Default> StreamLexer<X> {
+    pub fn new() -> Self {
+        Self::with_extras(X::default())
+    }
+}
+
+impl<X: Clone + Default> Default for StreamLexer<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: Clone> StreamLexer<X> {
+    /// Creates a stream lexer with the given initial extras.
+    pub fn with_extras(extras: X) -> Self {
+        Self {
+            checkpoint: Checkpoint { offset: 0, extras },
+            finished: false,
+        }
+    }
+
+    /// The byte offset at which the next token starts to be lexed.
+    pub fn offset(&self) -> usize {
+        self.checkpoint.offset
+    }
+
+    /// The extras after the last complete token.
+    pub fn extras(&self) -> &X {
+        &self.checkpoint.extras
+    }
+
+    /// Marks the end of the input, so that tokens at the end are returned.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Lexes the next complete token of `source`, which must contain all of
+    /// the input appended so far.
+    ///
+    /// The source is borrowed mutably, so that no other lexer can read from
+    /// it while the stream lexer checks whether the token is complete.
+    /// Lexing never starts before the [`LexStart::lex_start`] of `source`.
+    pub fn next_token<'s, T, C>(
+        &mut self,
+        source: &'s mut ChunkedSource<C>,
+    ) -> Streamed<Result<T, T::Error>>
+    where
+        T: Logos<'s, Source = ChunkedSource<C>, Extras = X>,
+        C: ChunkedText,
+    {
+        source.reset_read_past_end();
+        let source: &'s ChunkedSource<C> = source;
+
+        let mut lexer = Lexer::<T>::with_extras(source, self.checkpoint.extras.clone());
+        lexer.bump(self.checkpoint.offset.max(source.lex_start()));
+
+        let token = lexer.next();
+        // The token could still change if the lexer looked past the end of
+        // the input.
+        let complete =
+            self.finished || (lexer.span().end < source.len() && !source.read_past_end());
+
+        match token {
+            Some(token) if complete => {
+                let span = lexer.span();
+                self.checkpoint = Checkpoint {
+                    offset: span.end,
+                    extras: lexer.extras,
+                };
+                Streamed::Token(token, span)
+            }
+            None if self.finished => Streamed::Done,
+            _ => Streamed::NeedMoreInput,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ropey1"))]
+mod tests {
+    use super::*;
+    use crate::RopeSliceSource;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[regex("[a-z]+")]
+        Ident,
+        #[regex("[0-9]+")]
+        Number,
+        #[regex(r#""[^"]*""#)]
+        String,
+    }
+
+    #[test]
+    fn test_stream() {
+        let text = "abc 123 \"a string\"\nxyz 4 \"é\" 56789 q".repeat(20);
+
+        let rope = ropey::Rope::from_str(&text);
+        let expected: Vec<_> = logos::Lexer::<Token>::new(&RopeSliceSource::from(&rope))
+            .spanned()
+            .collect();
+
+        for step in [1, 2, 5, 13] {
+            let mut rope = ropey::Rope::new();
+            let mut stream = StreamLexer::new();
+            let mut tokens = Vec::new();
+            let mut chars = text.chars();
+
+            loop {
+                let mut source = RopeSliceSource::from(&rope);
+                match stream.next_token::<Token, _>(&mut source) {
+                    Streamed::Token(token, span) => tokens.push((token, span)),
+                    Streamed::NeedMoreInput => {
+                        let more: String = chars.by_ref().take(step).collect();
+                        if more.is_empty() {
+                            stream.finish();
+                        }
+                        rope.append(more.as_str().into());
+                    }
+                    Streamed::Done => break,
+                }
+            }
+
+            assert_eq!(tokens, expected, "step {step}");
+        }
+    }
+
+    /// Streams `inputs` one after the other, returning the tokens returned
+    /// after each one. The input is finished after the last one.
+    fn stream<T>(inputs: &[&str]) -> Vec<Vec<(Result<T, ()>, Range<usize>)>>
+    where
+        T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = (), Extras = ()>,
+    {
+        let mut rope = ropey::Rope::new();
+        let mut stream = StreamLexer::new();
+        let mut tokens = Vec::new();
+
+        for (i, input) in inputs.iter().enumerate() {
+            if i == inputs.len() - 1 {
+                stream.finish();
+            }
+            rope.append((*input).into());
+
+            let mut source = RopeSliceSource::from(&rope);
+            let mut new = Vec::new();
+            while let Streamed::Token(token, span) = stream.next_token::<T, _>(&mut source) {
+                new.push((token, span));
+            }
+            tokens.push(new);
+        }
+
+        tokens
+    }
+
+    #[test]
+    fn test_unclosed_string() {
+        let tokens = stream::<Token>(&["abc \"a", "b\" d"]);
+
+        // The error at the quote ends before the end of the input, but the
+        // string might still be closed, so it isn't returned.
+        assert!(tokens[0].iter().all(|(token, _)| token.is_ok()));
+        assert_eq!(
+            tokens.concat(),
+            [
+                (Ok(Token::Ident), 0..3),
+                (Ok(Token::String), 4..8),
+                (Ok(Token::Ident), 9..10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backtracking() {
+        #[derive(Logos, Debug, PartialEq)]
+        #[logos(source = RopeSliceSource<'s>)]
+        enum Literal {
+            #[token("ab")]
+            Ab,
+            #[token("abcd")]
+            Abcd,
+            #[token("function")]
+            Function,
+        }
+
+        // `ab` matches, but only after trying to read past the end for `abcd`.
+        assert_eq!(
+            stream::<Literal>(&["abc", "d", ""]),
+            [vec![], vec![], vec![(Ok(Literal::Abcd), 0..4)]]
+        );
+        // A literal longer than the rest of the input.
+        assert_eq!(
+            stream::<Literal>(&["func", "tion", ""]),
+            [vec![], vec![], vec![(Ok(Literal::Function), 0..8)]]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Ropes with particular chunk layouts, for the tests and the `testing`
+//! module.
+
+use ropey::{Rope, RopeBuilder};
+
+/// Builds a rope with exactly the given chunks, which must not be empty,
+/// longer than Ropey's maximum leaf size, or split a `\r\n`.
+///
+/// Ropey normally decides how to split text into chunks itself, so this
+/// makes it possible to test particular layouts, using Ropey's hidden
+/// builder APIs as described in `testing::chunk_layouts`.
+pub(crate) fn rope_from_chunks<'a>(chunks: impl IntoIterator<Item = &'a str>) -> Rope {
+    let mut builder = RopeBuilder::new();
+    for chunk in chunks {
+        builder._append_chunk(chunk);
+    }
+    builder._finish_no_fix()
+}
+
+/// Builds a rope with a chunk for every char of `text`.
+#[cfg(test)]
+pub(crate) fn char_rope(text: &str) -> Rope {
+    rope_from_chunks(text.char_indices().map(|(i, c)| &text[i..i + c.len_utf8()]))
+}
+
+/// Builds a rope with chunks of `chunk_len` bytes, which must not split a
+/// char.
+#[cfg(test)]
+pub(crate) fn chunked_rope(text: &str, chunk_len: usize) -> Rope {
+    rope_from_chunks(
+        text.as_bytes()
+            .chunks(chunk_len)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap()),
+    )
+}
@@ -0,0 +1,217 @@
+//! Differential testing of lexers over ropes, for checking that a grammar
+//! lexes the same however a rope is split into chunks.
+//!
+//! Logos reads several bytes at a time, so a grammar could behave
+//! differently where a read straddles two chunks, especially around
+//! multi-byte chars. The assertions here lex the text of a test case from
+//! ropes with many adversarial [`chunk_layouts`], and compare the tokens,
+//! spans and slices with lexing the same text from a single chunk, or from a
+//! `&str`:
+//!
+//! ```rust
+//! # use logos::Logos;
+//! # use logos_ropey::{testing, RopeSliceSource};
+//! #[derive(Logos, Debug, PartialEq)]
+//! #[logos(source = RopeSliceSource<'s>)]
+//! #[logos(skip " ")]
+//! enum Token {
+//!     #[regex("[a-zéèû]+")]
+//!     Word,
+//!     #[token("€")]
+//!     Euro,
+//! }
+//!
+//! testing::assert_chunking_invariant::<Token, _>("café €€ crème brûlée");
+//! ```
+
+use std::fmt::Debug;
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+use ropey::{Rope, RopeSlice};
+
+use crate::test_util::rope_from_chunks;
+use crate::RopeSliceSource;
+
+/// The largest chunk in the layouts which [`split`] the text.
+const MAX_CHUNK: usize = 64;
+
+/// Returns ropes containing `text` with a variety of chunk layouts.
+///
+/// The layouts include a chunk for every char, chunks of various small sizes,
+/// and chunks split right before and after every multi-byte char, as well as
+/// the layout Ropey itself would choose. A `\r\n` is never split, as Ropey
+/// counts it as a single line break.
+///
+/// Apart from Ropey's own layout, the ropes are built with Ropey's hidden
+/// `RopeBuilder::_append_chunk` and `_finish_no_fix`, which are exempt from
+/// its semver guarantees, so this may have to change with new versions of
+/// Ropey. Their chunks are also smaller than Ropey would ever make them, so
+/// the ropes are only meant for lexing. Editing them is unsupported, and
+/// their line and char indices may not be reliable.
+pub fn chunk_layouts(text: &str) -> Vec<Rope> {
+    let mut ropes = vec![Rope::from_str(text)];
+
+    for size in [1, 2, 3, 4, 5, 7, 16, MAX_CHUNK] {
+        ropes.push(split(text, |chunk, _| chunk.len() >= size));
+    }
+
+    // Split on both sides of every multi-byte char.
+    ropes.push(split(text, |chunk, next| {
+        !chunk.is_ascii() || !next.is_ascii()
+    }));
+    // Split only before or after multi-byte chars, to put them at the start
+    // or end of longer chunks.
+    ropes.push(split(text, |_, next| !next.is_ascii()));
+    ropes.push(split(text, |chunk, _| !chunk.is_ascii()));
+
+    ropes
+}
+
+/// Builds a rope from `text`, ending each chunk before the char `next` if
+/// `at(chunk, next)` is true, or if the chunk would grow too long, but never
+/// between a `\r` and a `\n`.
+fn split(text: &str, at: impl Fn(&str, char) -> bool) -> Rope {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        let chunk = &text[start..i];
+        if chunk.is_empty() || (c == '\n' && chunk.ends_with('\r')) {
+            continue;
+        }
+        if at(chunk, c) || chunk.len() + c.len_utf8() > MAX_CHUNK {
+            chunks.push(chunk);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        chunks.push(&text[start..]);
+    }
+
+    rope_from_chunks(chunks)
+}
+
+/// The tokens, spans and slices of lexing `slice`.
+fn lex<T, E>(slice: RopeSlice<'_>) -> Vec<(Result<T, E>, Range<usize>, String)>
+where
+    T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E>,
+    for<'s> <T as Logos<'s>>::Extras: Default,
+{
+    let source = RopeSliceSource::new(slice);
+    Lexer::<T>::new(&source)
+        .spanned()
+        .map(|(token, span)| {
+            let slice = logos::Source::slice(&source, span.clone()).expect("span out of bounds");
+            (token, span, String::from(slice))
+        })
+        .collect()
+}
+
+/// Asserts that lexing `text` from each of its [`chunk_layouts`] gives the
+/// same tokens, spans and slices as lexing it from a single chunk.
+///
+/// # Panics
+///
+/// Panics, showing the chunk lengths, if any layout lexes differently.
+pub fn assert_chunking_invariant<T, E>(text: &str)
+where
+    T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E> + PartialEq + Debug,
+    E: PartialEq + Debug,
+    for<'s> <T as Logos<'s>>::Extras: Default,
+{
+    let expected = lex::<T, E>(RopeSlice::from(text));
+
+    for rope in chunk_layouts(text) {
+        assert_eq!(
+            lex::<T, E>(rope.slice(..)),
+            expected,
+            "chunk lengths {:?}",
+            rope.chunks().map(str::len).collect::<Vec<_>>(),
+        );
+    }
+}
+
+/// Asserts that lexing `text` from each of its [`chunk_layouts`] with `R`
+/// gives the same tokens, spans and slices as lexing the `&str` with `S`.
+///
+/// `S` should be the same grammar as `R` with a `str` source, and tokens are
+/// compared by their `Debug` output, so that the two types can be compared.
+///
+/// # Panics
+///
+/// Panics, showing the chunk lengths, if any layout lexes differently.
+pub fn assert_lexes_like_str<R, RE, S, SE>(text: &str)
+where
+    R: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = RE> + Debug,
+    RE: Debug,
+    for<'s> <R as Logos<'s>>::Extras: Default,
+    S: for<'s> Logos<'s, Source = str, Error = SE> + Debug,
+    SE: Debug,
+    for<'s> <S as Logos<'s>>::Extras: Default,
+{
+    let expected: Vec<_> = Lexer::<S>::new(text)
+        .spanned()
+        .map(|(token, span)| (format!("{token:?}"), span.clone(), text[span].to_owned()))
+        .collect();
+
+    for rope in chunk_layouts(text) {
+        let actual: Vec<_> = lex::<R, RE>(rope.slice(..))
+            .into_iter()
+            .map(|(token, span, slice)| (format!("{token:?}"), span, slice))
+            .collect();
+
+        assert_eq!(
+            actual,
+            expected,
+            "chunk lengths {:?}",
+            rope.chunks().map(str::len).collect::<Vec<_>>(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(source = RopeSliceSource<'s>)]
+    #[logos(skip r"[ \r\n]+")]
+    enum Token {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    #[derive(Logos, Debug, PartialEq)]
+    #[logos(skip r"[ \r\n]+")]
+    enum StrToken {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    const TEXT: &str = "function functional funct é€😀 aé€😀b\r\n😀 ?functionalé\n";
+
+    #[test]
+    fn test_chunk_layouts() {
+        for rope in chunk_layouts(TEXT) {
+            assert_eq!(rope, TEXT);
+            assert!(rope.chunks().all(|chunk| !chunk.is_empty()));
+            assert!(rope.chunks().all(|chunk| !chunk.ends_with('\r')));
+        }
+    }
+
+    #[test]
+    fn test_assertions() {
+        assert_chunking_invariant::<Token, _>(TEXT);
+        assert_chunking_invariant::<Token, _>("");
+        assert_lexes_like_str::<Token, _, StrToken, _>(TEXT);
+    }
+}
@@ -0,0 +1,267 @@
+use std::ops::Range;
+
+use logos::{Lexer, Logos};
+use ropey::RopeSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::RopeSliceSource;
+
+/// A span relative to the end of the previous span.
+///
+/// Gaps and lengths are usually small, so they take up little space in
+/// formats with variable-length integers, such as postcard or bincode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeltaSpan {
+    /// Bytes between the end of the previous span, or the start of the text,
+    /// and the start of this span.
+    pub gap: usize,
+    pub len: usize,
+}
+
+/// Serializable tokens lexed from a rope, for caching lexing results or
+/// sending them to another process.
+///
+/// Spans are stored as [`DeltaSpan`]s, and the stream records the length and
+/// a [`content_hash`] of the text it was lexed from, so that cached tokens
+/// can be checked against the rope with [`TokenStream::is_valid_for`].
+///
+/// ```rust
+/// # use logos::Logos;
+/// # use logos_ropey::{RopeSliceSource, TokenStream};
+/// #[derive(Logos, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// #[logos(source = RopeSliceSource<'s>)]
+/// #[logos(skip " ")]
+/// enum Token {
+///     #[regex("[a-z]+")]
+///     Word,
+/// }
+///
+/// let rope = ropey::Rope::from_str("one two");
+/// let stream = TokenStream::<Result<Token, ()>>::lex(rope.slice(..));
+///
+/// assert!(stream.is_valid_for(rope.slice(..)));
+/// assert!(!stream.is_valid_for(rope.slice(1..)));
+/// assert_eq!(stream.into_tokens(), [(Ok(Token::Word), 0..3), (Ok(Token::Word), 4..7)]);
+/// ```
+///
+/// Deserializing a stream checks that it has a span for every token, and that
+/// the spans lie within the text, so that a corrupt stream is an error rather
+/// than losing tokens or overflowing.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "RawTokenStream<T>")]
+pub struct TokenStream<T> {
+    len: usize,
+    content_hash: u64,
+    tokens: Vec<T>,
+    spans: Vec<DeltaSpan>,
+}
+
+/// A [`TokenStream`] which hasn't been validated yet.
+#[derive(Deserialize)]
+struct RawTokenStream<T> {
+    len: usize,
+    content_hash: u64,
+    tokens: Vec<T>,
+    spans: Vec<DeltaSpan>,
+}
+
+impl<T> TryFrom<RawTokenStream<T>> for TokenStream<T> {
+    type Error = InvalidTokenStream;
+
+    fn try_from(raw: RawTokenStream<T>) -> Result<Self, InvalidTokenStream> {
+        if raw.tokens.len() != raw.spans.len() {
+            return Err(InvalidTokenStream("the number of tokens and spans differ"));
+        }
+
+        let mut end = 0usize;
+        for delta in &raw.spans {
+            end = end
+                .checked_add(delta.gap)
+                .and_then(|start| start.checked_add(delta.len))
+                .filter(|&end| end <= raw.len)
+                .ok_or(InvalidTokenStream("a span is out of bounds"))?;
+        }
+
+        Ok(Self {
+            len: raw.len,
+            content_hash: raw.content_hash,
+            tokens: raw.tokens,
+            spans: raw.spans,
+        })
+    }
+}
+
+/// Why a deserialized [`TokenStream`] is invalid.
+#[derive(Debug)]
+struct InvalidTokenStream(&'static str);
+
+impl std::fmt::Display for InvalidTokenStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid token stream: {}", self.0)
+    }
+}
+
+impl<T> TokenStream<T> {
+    /// Creates a stream from the spanned tokens of `text`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the spans aren't in order, overlap, or are out of bounds.
+    pub fn new(text: RopeSlice<'_>, tokens: impl IntoIterator<Item = (T, Range<usize>)>) -> Self {
+        let len = text.len_bytes();
+        let tokens = tokens.into_iter();
+
+        let mut stream = Self {
+            len,
+            content_hash: content_hash(text),
+            tokens: Vec::with_capacity(tokens.size_hint().0),
+            spans: Vec::with_capacity(tokens.size_hint().0),
+        };
+
+        let mut end = 0;
+        for (token, span) in tokens {
+            assert!(
+                end <= span.start && span.start <= span.end && span.end <= len,
+                "span {span:?} out of order or out of bounds",
+            );
+
+            stream.tokens.push(token);
+            stream.spans.push(DeltaSpan {
+                gap: span.start - end,
+                len: span.len(),
+            });
+            end = span.end;
+        }
+
+        stream
+    }
+
+    /// The length in bytes of the text the tokens were lexed from.
+    pub fn text_len(&self) -> usize {
+        self.len
+    }
+
+    /// The [`content_hash`] of the text the tokens were lexed from.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// Whether the tokens were lexed from text with the same contents as
+    /// `text`, as far as its length and hash can tell.
+    pub fn is_valid_for(&self, text: RopeSlice<'_>) -> bool {
+        text.len_bytes() == self.len && content_hash(text) == self.content_hash
+    }
+
+    /// The number of tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The tokens and their absolute spans.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Range<usize>)> + '_ {
+        self.tokens.iter().zip(self.spans())
+    }
+
+    /// The absolute spans of the tokens.
+    pub fn spans(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.spans.iter().scan(0, |end, delta| {
+            let start = *end + delta.gap;
+            *end = start + delta.len;
+            Some(start..*end)
+        })
+    }
+
+    /// The tokens and their absolute spans.
+    pub fn into_tokens(self) -> Vec<(T, Range<usize>)> {
+        let spans: Vec<_> = self.spans().collect();
+        self.tokens.into_iter().zip(spans).collect()
+    }
+}
+
+impl<T, E> TokenStream<Result<T, E>> {
+    /// Lexes all of `text`.
+    pub fn lex(text: RopeSlice<'_>) -> Self
+    where
+        T: for<'s> Logos<'s, Source = RopeSliceSource<'s>, Error = E>,
+        for<'s> <T as Logos<'s>>::Extras: Default,
+    {
+        let source = RopeSliceSource::new(text);
+        Self::new(text, Lexer::<T>::new(&source).spanned())
+    }
+}
+
+/// Hashes the contents of `text` with 64-bit FNV-1a.
+///
+/// Unlike the standard library's hashers, the hash is the same on every
+/// platform and in every process, and doesn't depend on how the text is split
+/// into chunks.
+pub fn content_hash(text: RopeSlice<'_>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    text.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::char_rope;
+
+    #[test]
+    fn test_token_stream() {
+        let tokens = [('a', 2..5), ('b', 5..5), ('c', 9..12)];
+        let rope = ropey::Rope::from_str("0123456789abcdef");
+        let stream = TokenStream::new(rope.slice(..), tokens.clone());
+
+        assert_eq!(
+            stream.spans,
+            [
+                DeltaSpan { gap: 2, len: 3 },
+                DeltaSpan { gap: 0, len: 0 },
+                DeltaSpan { gap: 4, len: 3 },
+            ],
+        );
+        assert_eq!(stream.into_tokens(), tokens);
+    }
+
+    #[test]
+    fn test_validate() {
+        let raw = |tokens: Vec<char>, spans: Vec<(usize, usize)>| RawTokenStream {
+            len: 10,
+            content_hash: 0,
+            tokens,
+            spans: spans
+                .into_iter()
+                .map(|(gap, len)| DeltaSpan { gap, len })
+                .collect(),
+        };
+
+        assert!(TokenStream::try_from(raw(vec!['a', 'b'], vec![(2, 3), (5, 0)])).is_ok());
+        // Missing spans.
+        assert!(TokenStream::try_from(raw(vec!['a', 'b'], vec![(2, 3)])).is_err());
+        // A span past the end of the text.
+        assert!(TokenStream::try_from(raw(vec!['a', 'b'], vec![(2, 3), (5, 1)])).is_err());
+        // Spans which overflow.
+        assert!(TokenStream::try_from(raw(vec!['a'], vec![(usize::MAX, 1)])).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash("".into()), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash("a".into()), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(content_hash("foobar".into()), 0x8594_4171_f739_67e8);
+
+        let text = "é€😀 hash me".repeat(10);
+        let rope = char_rope(&text);
+        assert_eq!(
+            content_hash(rope.slice(..)),
+            content_hash(text.as_str().into())
+        );
+    }
+}
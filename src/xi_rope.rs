@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::chunked::{is_continuation, ChunkedSource, ChunkedText};
+
+/// A [`logos::Source`] which owns a [`xi_rope::Rope`].
+///
+/// Xi ropes have no borrowed slice type, so token slices are
+/// [`Cow<str>`](Cow)s which borrow whenever the token lies within one chunk.
+pub type XiRopeSource = ChunkedSource<::xi_rope::Rope>;
+
+// SAFETY: Xi stores chunks in separate reference-counted leaves.
+unsafe impl ChunkedText for ::xi_rope::Rope {
+    type Slice<'a> = Cow<'a, str>;
+
+    fn len_bytes(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk_at(&self, offset: usize) -> (&str, usize) {
+        // Chunks can only start on a char boundary.
+        let mut start = offset;
+        while is_continuation(self.byte_at(start)) {
+            start -= 1;
+        }
+
+        let chunk = self
+            .iter_chunks(start..)
+            .find(|chunk| !chunk.is_empty())
+            .expect("offset out of bounds");
+        (chunk, start)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Option<Self::Slice<'_>> {
+        let valid = range.start <= range.end
+            && range.end <= self.len()
+            && self.is_codepoint_boundary(range.start)
+            && self.is_codepoint_boundary(range.end);
+        valid.then(|| self.slice_to_cow(range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked::tests::{assert_lexes_like_str, edits, texts};
+
+    #[derive(logos::Logos, Debug)]
+    #[logos(source = XiRopeSource)]
+    #[logos(skip r"[ \n]+")]
+    enum Token {
+        #[token("function")]
+        Function,
+        #[token("functional")]
+        Functional,
+        #[regex("[a-zé€😀]+")]
+        Word,
+    }
+
+    #[test]
+    fn test_lexes_like_str() {
+        for text in texts() {
+            let mut rope = ::xi_rope::Rope::from(text.as_str());
+            for range in edits(&text) {
+                rope.edit(range.clone(), &text[range]);
+            }
+
+            assert_lexes_like_str::<Token>(&XiRopeSource::new(rope), &text);
+        }
+    }
+}